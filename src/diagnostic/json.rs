@@ -0,0 +1,58 @@
+use super::Diagnostic;
+use crate::span::{SourceMap, Span};
+use serde_json::{Value, json};
+
+/// A diagnostic as a single JSON object. `rendered` is the human-readable
+/// form, for tools that just want to show it.
+pub fn diagnostic(diagnostic: &Diagnostic, source_map: &SourceMap, rendered: String) -> Value {
+    let primary_label = diagnostic
+        .labels
+        .iter()
+        .find(|label| label.span == diagnostic.span)
+        .map(|label| label.message.as_str());
+    let mut spans = vec![span(diagnostic.span, source_map, true, primary_label)];
+    spans.extend(
+        diagnostic
+            .labels
+            .iter()
+            .filter(|label| label.span != diagnostic.span)
+            .map(|label| span(label.span, source_map, false, Some(&label.message))),
+    );
+    json!({
+        "severity": diagnostic.severity.name(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "spans": spans,
+        "notes": diagnostic.notes,
+        "help": diagnostic.help,
+        "suggestions": diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| json!({
+                "message": suggestion.message,
+                "replacement": suggestion.replacement,
+                "span": span(suggestion.span, source_map, false, None),
+            }))
+            .collect::<Vec<_>>(),
+        "rendered": rendered,
+    })
+}
+
+/// Lines and columns are 1-based, with columns counted in characters; the
+/// end position is exclusive.
+fn span(span: Span, source_map: &SourceMap, is_primary: bool, label: Option<&str>) -> Value {
+    let file = source_map.file(span.file);
+    let (line_start, column_start) = file.line_col(span.start);
+    let (line_end, column_end) = file.line_col(span.end);
+    json!({
+        "file": file.name,
+        "byte_start": span.start,
+        "byte_end": span.end,
+        "line_start": line_start,
+        "column_start": column_start,
+        "line_end": line_end,
+        "column_end": column_end,
+        "is_primary": is_primary,
+        "label": label,
+    })
+}
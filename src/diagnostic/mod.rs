@@ -0,0 +1,140 @@
+mod json;
+mod render;
+mod sarif;
+pub mod suggest;
+
+use crate::span::Span;
+pub use render::Emitter;
+
+/// How diagnostics are written out.
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// Rendered for reading, with source snippets.
+    Human,
+    /// One JSON object per line.
+    Json,
+    /// A single SARIF 2.1.0 log.
+    Sarif,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Stable identifiers for every kind of problem, so tooling can filter on
+/// them without matching message text.
+pub mod codes {
+    pub const UNEXPECTED_CHARACTER: &str = "E0001";
+    pub const UNTERMINATED_COMMENT: &str = "E0002";
+    pub const UNTERMINATED_LITERAL: &str = "E0003";
+    pub const INVALID_NUMBER: &str = "E0004";
+    pub const NUMBER_OUT_OF_RANGE: &str = "E0005";
+    pub const INVALID_CHAR_LITERAL: &str = "E0006";
+    pub const UNSUPPORTED_LIFETIME: &str = "E0007";
+    pub const INVALID_ESCAPE: &str = "E0008";
+    pub const NON_ASCII_BYTE: &str = "E0009";
+    pub const INVALID_INTERPOLATION: &str = "E0010";
+    pub const UNMATCHED_DELIMITER: &str = "E0011";
+    pub const UNEXPECTED_TOKEN: &str = "E0012";
+    pub const MISSING_SEMICOLON: &str = "E0013";
+    pub const INVALID_ASSIGNMENT_TARGET: &str = "E0014";
+    pub const MISPLACED_DOC_COMMENT: &str = "E0015";
+    pub const INVALID_PIPE: &str = "E0016";
+    pub const ASSIGN_TO_IMMUTABLE: &str = "E0017";
+    pub const UNKNOWN_KEYWORD: &str = "E0018";
+}
+
+/// A span with a message explaining its part in a diagnostic.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A proposed edit: replace the text at `span` with `replacement`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A problem found in the source, reported by any stage of the front end.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// Where the problem is.
+    pub span: Span,
+    /// Other places that help explain the problem.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message, span)
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        message: impl Into<String>,
+        span: Span,
+        replacement: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            message: message.into(),
+            span,
+            replacement: replacement.into(),
+        });
+        self
+    }
+}
@@ -0,0 +1,355 @@
+use super::{Diagnostic, ErrorFormat, Severity, Suggestion, json, sarif};
+use crate::span::{FileId, SourceFile, SourceMap, Span, TAB_WIDTH, width};
+use std::io::IsTerminal;
+
+const BOLD: &str = "1";
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+const BLUE: &str = "1;34";
+const GREEN: &str = "1;32";
+
+/// Prints diagnostics to stderr in the chosen [`ErrorFormat`]. Human output
+/// is in the style of rustc: a header, the source lines the diagnostic points
+/// at with their spans underlined, then any notes and help. Keeps count of
+/// what it printed so it can end with a summary.
+pub struct Emitter {
+    format: ErrorFormat,
+    colour: bool,
+    errors: usize,
+    warnings: usize,
+    /// SARIF results, held back until [`Emitter::finish`] writes the whole log.
+    sarif_results: Vec<serde_json::Value>,
+}
+
+/// One underlined span in a snippet.
+struct Annotation<'a> {
+    span: Span,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+impl Emitter {
+    pub fn new(format: ErrorFormat, colour: bool) -> Self {
+        Emitter {
+            format,
+            // Machine-readable output never contains escape codes.
+            colour: colour && format == ErrorFormat::Human,
+            errors: 0,
+            warnings: 0,
+            sarif_results: Vec::new(),
+        }
+    }
+
+    /// Whether to colour output by default: only when stderr is a terminal and
+    /// the user hasn't opted out with `NO_COLOR`.
+    pub fn detect_colour() -> bool {
+        std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+    }
+
+    pub fn emit(&mut self, diagnostic: &Diagnostic, source_map: &SourceMap) {
+        match diagnostic.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        match self.format {
+            ErrorFormat::Human => eprintln!("{}\n", self.render(diagnostic, source_map)),
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                json::diagnostic(diagnostic, source_map, self.render(diagnostic, source_map))
+            ),
+            ErrorFormat::Sarif => self
+                .sarif_results
+                .push(sarif::result(diagnostic, source_map)),
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// Writes whatever closes the output: the summary for humans, or the
+    /// complete SARIF log.
+    pub fn finish(&mut self) {
+        match self.format {
+            ErrorFormat::Human => {
+                if let Some(summary) = self.summary() {
+                    eprintln!("{}", summary);
+                }
+            }
+            ErrorFormat::Json => {}
+            ErrorFormat::Sarif => {
+                eprintln!("{:#}", sarif::log(std::mem::take(&mut self.sarif_results)))
+            }
+        }
+    }
+
+    /// A closing line counting everything emitted, or `None` if there was nothing.
+    fn summary(&self) -> Option<String> {
+        let warnings = match self.warnings {
+            0 => None,
+            n => Some(format!("{} warning{} emitted", n, plural(n))),
+        };
+        match (self.errors, warnings) {
+            (0, None) => None,
+            (0, Some(warnings)) => Some(self.header(Severity::Warning, None, &warnings)),
+            (n, warnings) => {
+                let mut message =
+                    format!("could not compile due to {} previous error{}", n, plural(n));
+                if let Some(warnings) = warnings {
+                    message += &format!("; {}", warnings);
+                }
+                Some(self.header(Severity::Error, None, &message))
+            }
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, source_map: &SourceMap) -> String {
+        // A label on the primary span is shown next to its carets rather than
+        // as a separate secondary annotation.
+        let primary_label = diagnostic
+            .labels
+            .iter()
+            .position(|label| label.span == diagnostic.span);
+        let mut annotations = vec![Annotation {
+            span: diagnostic.span,
+            message: primary_label.map(|i| diagnostic.labels[i].message.as_str()),
+            primary: true,
+        }];
+        annotations.extend(
+            diagnostic
+                .labels
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != primary_label)
+                .map(|(_, label)| Annotation {
+                    span: label.span,
+                    message: Some(label.message.as_str()),
+                    primary: false,
+                }),
+        );
+
+        let gutter = annotations
+            .iter()
+            .map(|a| a.span)
+            .chain(diagnostic.suggestions.iter().map(|s| s.span))
+            .map(|span| source_map.line_col(span).0)
+            .max()
+            .unwrap_or(1)
+            .to_string()
+            .len();
+
+        let mut out = self.header(
+            diagnostic.severity,
+            Some(diagnostic.code),
+            &diagnostic.message,
+        );
+
+        // The primary span's file comes first; labels elsewhere get their own snippets.
+        let mut files: Vec<FileId> = Vec::new();
+        for annotation in &annotations {
+            if !files.contains(&annotation.span.file) {
+                files.push(annotation.span.file);
+            }
+        }
+        for (i, &file) in files.iter().enumerate() {
+            let in_file: Vec<&Annotation> =
+                annotations.iter().filter(|a| a.span.file == file).collect();
+            let (line, column) = source_map.line_col(in_file[0].span);
+            let arrow = if i == 0 { "-->" } else { ":::" };
+            out += &format!(
+                "\n{}{} {}:{}:{}",
+                " ".repeat(gutter),
+                self.paint(arrow, BLUE),
+                source_map.file(file).name,
+                line,
+                column
+            );
+            out += &self.snippet(source_map.file(file), &in_file, diagnostic.severity, gutter);
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            out += &format!("\n{}", self.gutter_line(gutter));
+        }
+        for note in &diagnostic.notes {
+            out += &format!(
+                "\n{} {} {}: {}",
+                " ".repeat(gutter),
+                self.paint("=", BLUE),
+                self.paint("note", BOLD),
+                note
+            );
+        }
+        for help in &diagnostic.help {
+            out += &format!(
+                "\n{} {} {}: {}",
+                " ".repeat(gutter),
+                self.paint("=", BLUE),
+                self.paint("help", BOLD),
+                help
+            );
+        }
+
+        for suggestion in &diagnostic.suggestions {
+            out += &self.suggestion(suggestion, source_map, gutter);
+        }
+        out
+    }
+
+    fn header(&self, severity: Severity, code: Option<&str>, message: &str) -> String {
+        let level = match code {
+            Some(code) => format!("{}[{}]", severity.name(), code),
+            None => severity.name().to_string(),
+        };
+        format!(
+            "{}{}",
+            self.paint(&level, severity_colour(severity)),
+            self.paint(&format!(": {}", message), BOLD)
+        )
+    }
+
+    /// The quoted source lines for the annotations in one file, each followed by
+    /// a row of underlines per annotation on that line.
+    fn snippet(
+        &self,
+        file: &SourceFile,
+        annotations: &[&Annotation],
+        severity: Severity,
+        gutter: usize,
+    ) -> String {
+        let mut lines: Vec<usize> = annotations
+            .iter()
+            .map(|a| file.line_col(a.span.start).0)
+            .collect();
+        lines.sort();
+        lines.dedup();
+
+        let mut out = format!("\n{}", self.gutter_line(gutter));
+        let mut previous: Option<usize> = None;
+        for &line in &lines {
+            match previous {
+                // Show a single skipped line rather than eliding it.
+                Some(p) if line == p + 2 => out += &self.source_line(file, p + 1, gutter),
+                Some(p) if line > p + 2 => out += &format!("\n{}", self.paint("...", BLUE)),
+                _ => {}
+            }
+            out += &self.source_line(file, line, gutter);
+
+            let mut on_line: Vec<&&Annotation> = annotations
+                .iter()
+                .filter(|a| file.line_col(a.span.start).0 == line)
+                .collect();
+            on_line.sort_by_key(|a| (a.span.start, !a.primary));
+            for annotation in on_line {
+                let (mark, colour) = if annotation.primary {
+                    ("^", severity_colour(severity))
+                } else {
+                    ("-", BLUE)
+                };
+                let (indent, width) = underline_extent(file, line, annotation.span);
+                let mut underline = mark.repeat(width);
+                if let Some(message) = annotation.message {
+                    underline += &format!(" {}", message);
+                }
+                out += &format!(
+                    "\n{}{}{}",
+                    self.gutter_line(gutter),
+                    " ".repeat(indent + 1),
+                    self.paint(&underline, colour)
+                );
+            }
+            previous = Some(line);
+        }
+        out
+    }
+
+    /// A suggestion shown as its line with the replacement applied and the
+    /// new text marked, or as a one-line help when it spans several lines.
+    fn suggestion(&self, suggestion: &Suggestion, source_map: &SourceMap, gutter: usize) -> String {
+        if !is_single_line(suggestion.span, source_map) {
+            return format!(
+                "\n{} {} {}: {}: '{}'",
+                " ".repeat(gutter),
+                self.paint("=", BLUE),
+                self.paint("help", BOLD),
+                suggestion.message,
+                suggestion.replacement
+            );
+        }
+        let file = source_map.file(suggestion.span.file);
+        let (line, _) = file.line_col(suggestion.span.start);
+        let start = file.line_start(line);
+        let text = file.line(line);
+        let before = &text[..(suggestion.span.start - start).min(text.len())];
+        let after = &text[(suggestion.span.end - start).min(text.len())..];
+        let mark = if suggestion.span.start == suggestion.span.end {
+            "+"
+        } else {
+            "~"
+        };
+        format!(
+            "\n{}: {}\n{}\n{} {}{}{}\n{}{}{}",
+            self.paint("help", BOLD),
+            suggestion.message,
+            self.gutter_line(gutter),
+            self.paint(&format!("{:>width$} |", line, width = gutter), BLUE),
+            expand_tabs(before),
+            expand_tabs(&suggestion.replacement),
+            expand_tabs(after),
+            self.gutter_line(gutter),
+            " ".repeat(width(before) + 1),
+            self.paint(&mark.repeat(width(&suggestion.replacement).max(1)), GREEN)
+        )
+    }
+
+    fn source_line(&self, file: &SourceFile, line: usize, gutter: usize) -> String {
+        format!(
+            "\n{} {}",
+            self.paint(&format!("{:>width$} |", line, width = gutter), BLUE),
+            expand_tabs(file.line(line))
+        )
+    }
+
+    /// An empty gutter: `  |`.
+    fn gutter_line(&self, gutter: usize) -> String {
+        self.paint(&format!("{} |", " ".repeat(gutter)), BLUE)
+    }
+
+    fn paint(&self, text: &str, style: &str) -> String {
+        if self.colour {
+            format!("\x1b[{}m{}\x1b[0m", style, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn severity_colour(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+fn is_single_line(span: Span, source_map: &SourceMap) -> bool {
+    let file = source_map.file(span.file);
+    file.line_col(span.start).0 == file.line_col(span.end).0
+}
+
+/// The column an underline for `span` starts at on `line`, and how many
+/// columns it covers. Spans running past the end of the line are cut off
+/// there; empty spans still get a single mark.
+fn underline_extent(file: &SourceFile, line: usize, span: Span) -> (usize, usize) {
+    let start = file.line_start(line);
+    let text = file.line(line);
+    let from = (span.start - start).min(text.len());
+    let to = (span.end.max(span.start) - start).min(text.len());
+    (width(&text[..from]), width(&text[from..to]).max(1))
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
@@ -0,0 +1,100 @@
+use super::{Diagnostic, Severity};
+use crate::span::{SourceMap, Span};
+use serde_json::{Value, json};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A complete SARIF 2.1.0 log for one run over the given results.
+pub fn log(results: Vec<Value>) -> Value {
+    let mut rules: Vec<&str> = results
+        .iter()
+        .filter_map(|result| result["ruleId"].as_str())
+        .collect();
+    rules.sort();
+    rules.dedup();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            // Columns count characters, as everywhere else in our output.
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }]
+    })
+}
+
+/// One diagnostic as a SARIF result. Notes and help have no place of their
+/// own in SARIF, so they are appended to the message text.
+pub fn result(diagnostic: &Diagnostic, source_map: &SourceMap) -> Value {
+    let mut text = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        text += &format!("\nnote: {}", note);
+    }
+    for help in &diagnostic.help {
+        text += &format!("\nhelp: {}", help);
+    }
+    json!({
+        "ruleId": diagnostic.code,
+        "level": match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        "message": { "text": text },
+        "locations": [{ "physicalLocation": physical_location(diagnostic.span, source_map) }],
+        "relatedLocations": diagnostic
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(id, label)| json!({
+                "id": id,
+                "physicalLocation": physical_location(label.span, source_map),
+                "message": { "text": label.message },
+            }))
+            .collect::<Vec<_>>(),
+        "fixes": diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| json!({
+                "description": { "text": suggestion.message },
+                "artifactChanges": [{
+                    "artifactLocation": artifact_location(suggestion.span, source_map),
+                    "replacements": [{
+                        "deletedRegion": {
+                            "byteOffset": suggestion.span.start,
+                            "byteLength": suggestion.span.end - suggestion.span.start,
+                        },
+                        "insertedContent": { "text": suggestion.replacement },
+                    }],
+                }],
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn physical_location(span: Span, source_map: &SourceMap) -> Value {
+    let file = source_map.file(span.file);
+    let (start_line, start_column) = file.line_col(span.start);
+    let (end_line, end_column) = file.line_col(span.end);
+    json!({
+        "artifactLocation": artifact_location(span, source_map),
+        "region": {
+            "startLine": start_line,
+            "startColumn": start_column,
+            "endLine": end_line,
+            "endColumn": end_column,
+            "byteOffset": span.start,
+            "byteLength": span.end - span.start,
+        },
+    })
+}
+
+fn artifact_location(span: Span, source_map: &SourceMap) -> Value {
+    json!({ "uri": source_map.file(span.file).name })
+}
@@ -1,38 +1,37 @@
+mod number;
+mod string;
 pub mod types;
 
 use std::{iter::Peekable, str::Chars};
 
-use types::{Op, StringParser, Token, TokenKind};
+use types::{Op, StringPart, Token, TokenKind};
 use unicode_ident::{is_xid_continue, is_xid_start};
 
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    span::{FileId, Span},
+};
+
 pub struct LexerCursor<'a> {
     source: Peekable<Chars<'a>>,
     position: usize,
-    column: usize,
-    row: usize,
     src: &'a str,
+    file: FileId,
 }
 
 impl<'a> LexerCursor<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, file: FileId) -> Self {
         LexerCursor {
             source: input.chars().peekable(),
             position: 0,
-            column: 0,
-            row: 1,
             src: input,
+            file,
         }
     }
 
     pub fn bump(&mut self) -> Option<char> {
         let ch = self.source.next();
         if let Some(c) = ch {
-            if c == '\n' {
-                self.row += 1;
-                self.column = 0;
-            } else {
-                self.column += 1;
-            }
             self.position += c.len_utf8()
         }
         ch
@@ -42,8 +41,84 @@ impl<'a> LexerCursor<'a> {
         self.source.peek().copied()
     }
 
-    pub fn pos(&self) -> (usize, usize) {
-        (self.row, self.column)
+    fn rest(&self) -> &'a str {
+        &self.src[self.position..]
+    }
+
+    fn at_doc_comment(&self) -> bool {
+        let rest = self.rest();
+        (rest.starts_with("///") && !rest.starts_with("////")) || rest.starts_with("//!")
+    }
+
+    /// Skips whitespace and non-doc comments, stopping at the next token.
+    /// An unterminated block comment is returned as an error token.
+    fn skip_trivia(&mut self) -> Result<(), Token> {
+        loop {
+            self.eat_while(char::is_whitespace);
+            if self.rest().starts_with("//") && !self.at_doc_comment() {
+                self.eat_while(|c| c != '\n');
+            } else if self.rest().starts_with("/*") {
+                let start = self.position;
+                self.eat_block_comment()
+                    .map_err(|e| self.error_token(start, e))?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips a `/* .. */` comment, which may contain nested block comments.
+    fn eat_block_comment(&mut self) -> Result<(), Box<Diagnostic>> {
+        let start = self.position;
+        self.bump();
+        self.bump();
+        let mut depth = 1;
+        while depth > 0 {
+            if self.rest().starts_with("/*") {
+                self.bump();
+                self.bump();
+                depth += 1;
+            } else if self.rest().starts_with("*/") {
+                self.bump();
+                self.bump();
+                depth -= 1;
+            } else if self.bump().is_none() {
+                return Err(Diagnostic::error(
+                    codes::UNTERMINATED_COMMENT,
+                    "Unterminated block comment",
+                    Span::new(self.file, start, start + 2),
+                )
+                .with_note("block comments nest, so every '/*' needs its own '*/'")
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// An error token for the source consumed since `start`.
+    fn error_token(&self, start: usize, diagnostic: Box<Diagnostic>) -> Token {
+        Token {
+            kind: TokenKind::Error {
+                text: self.src[start..self.position].to_string(),
+                diagnostic,
+            },
+            span: self.span_from(start),
+        }
+    }
+
+    /// The span from byte `start` up to the current position.
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(self.file, start, self.position)
+    }
+
+    /// Lexes `op`, or `assign` when the operator is directly followed by `=`.
+    fn op_or_assign(&mut self, op: Op, assign: Op) -> TokenKind {
+        if self.peek() == Some('=') {
+            self.bump();
+            TokenKind::Op(assign)
+        } else {
+            TokenKind::Op(op)
+        }
     }
 
     fn eat_while<F>(&mut self, mut condition: F) -> &'a str
@@ -62,16 +137,48 @@ impl<'a> LexerCursor<'a> {
     }
 }
 
+/// The diagnostics of all error tokens in `tokens`, including those inside
+/// interpolated strings, in source order.
+pub fn errors(tokens: &[Token]) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    for token in tokens {
+        match &token.kind {
+            TokenKind::Error { diagnostic, .. } => errors.push((**diagnostic).clone()),
+            TokenKind::InterpolatedString(parts) => {
+                for part in parts {
+                    if let StringPart::Hole(tokens) = part {
+                        errors.extend(self::errors(tokens));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
 impl Iterator for LexerCursor<'_> {
-    type Item = Result<Token, String>;
+    type Item = Token;
 
+    /// Lexes the next token. Malformed input becomes a `TokenKind::Error`
+    /// token covering the offending text, and lexing continues after it.
     fn next(&mut self) -> Option<Self::Item> {
-        self.eat_while(char::is_whitespace);
+        if let Err(error) = self.skip_trivia() {
+            return Some(error);
+        }
+        let start = self.position;
         let c = self.bump()?;
-        let (line, column) = self.pos();
         let tokenskind = match c {
             ',' => TokenKind::Comma,
-            ':' => TokenKind::Colon,
+            ':' => {
+                if self.peek() == Some(':') {
+                    self.bump();
+                    TokenKind::PathSep
+                } else {
+                    TokenKind::Colon
+                }
+            }
+            '.' => TokenKind::Dot,
             ';' => TokenKind::SemiColon,
             '(' => TokenKind::LParen,
             ')' => TokenKind::RParen,
@@ -79,19 +186,35 @@ impl Iterator for LexerCursor<'_> {
             '}' => TokenKind::RCurly,
             '[' => TokenKind::LBracket,
             ']' => TokenKind::RBracket,
-            '+' => TokenKind::Op(Op::Plus),
-            '-' => TokenKind::Op(Op::Minus),
-            '*' => TokenKind::Op(Op::Multiply),
-            '/' => TokenKind::Op(Op::Divide),
-            '%' => TokenKind::Op(Op::Modulo),
-            '=' => {
-                if self.peek() == Some('=') {
+            '+' => self.op_or_assign(Op::Plus, Op::PlusAssign),
+            '-' => self.op_or_assign(Op::Minus, Op::MinusAssign),
+            '*' => self.op_or_assign(Op::Multiply, Op::MultiplyAssign),
+            '/' => {
+                // Plain comments were skipped as trivia, so `//` here starts a doc comment.
+                if self.peek() == Some('/') {
                     self.bump();
-                    TokenKind::Op(Op::EqualTo)
+                    self.bump();
+                    TokenKind::DocComment(
+                        self.eat_while(|c| c != '\n')
+                            .trim_end_matches('\r')
+                            .to_string(),
+                    )
                 } else {
-                    TokenKind::Op(Op::Equal)
+                    self.op_or_assign(Op::Divide, Op::DivideAssign)
                 }
             }
+            '%' => self.op_or_assign(Op::Modulo, Op::ModuloAssign),
+            '=' => match self.peek() {
+                Some('=') => {
+                    self.bump();
+                    TokenKind::Op(Op::EqualTo)
+                }
+                Some('>') => {
+                    self.bump();
+                    TokenKind::FatArrow
+                }
+                _ => TokenKind::Op(Op::Equal),
+            },
             '!' => {
                 if self.peek() == Some('=') {
                     self.bump();
@@ -107,7 +230,7 @@ impl Iterator for LexerCursor<'_> {
                 }
                 Some('>') => {
                     self.bump();
-                    TokenKind::Op(Op::ShiftRight)
+                    self.op_or_assign(Op::ShiftRight, Op::ShiftRightAssign)
                 }
                 _ => TokenKind::Op(Op::GreaterThan),
             },
@@ -118,7 +241,7 @@ impl Iterator for LexerCursor<'_> {
                 }
                 Some('<') => {
                     self.bump();
-                    TokenKind::Op(Op::ShiftLeft)
+                    self.op_or_assign(Op::ShiftLeft, Op::ShiftLeftAssign)
                 }
                 _ => TokenKind::Op(Op::LessThan),
             },
@@ -127,7 +250,7 @@ impl Iterator for LexerCursor<'_> {
                     self.bump();
                     TokenKind::Op(Op::And)
                 } else {
-                    TokenKind::Op(Op::BitAnd)
+                    self.op_or_assign(Op::BitAnd, Op::BitAndAssign)
                 }
             }
             '|' => match self.peek() {
@@ -139,48 +262,38 @@ impl Iterator for LexerCursor<'_> {
                     self.bump();
                     TokenKind::Op(Op::Pipe)
                 }
-                _ => TokenKind::Op(Op::BitOr),
+                _ => self.op_or_assign(Op::BitOr, Op::BitOrAssign),
             },
-            '^' => TokenKind::Op(Op::BitXor),
+            '^' => self.op_or_assign(Op::BitXor, Op::BitXorAssign),
             '~' => TokenKind::Op(Op::BitNot),
-            '"' => {
-                let mut string_parser = StringParser::new();
-                let string_content = self.eat_while(|c| string_parser.condition(c));
-                if self.peek() == Some('"') {
-                    self.bump();
-                    TokenKind::String(string_content.to_string())
-                } else {
-                    return None;
-                }
-            }
-            c if c.is_ascii_digit() => {
-                let mut number_str = c.to_string();
-                number_str.push_str(self.eat_while(|ch| ch.is_ascii_digit()));
-                if self.peek() == Some('.') {
-                    number_str.push('.');
-                    self.bump();
-                    number_str.push_str(self.eat_while(|ch| ch.is_ascii_digit()));
-                    match number_str.parse::<f64>() {
-                        Ok(num) => TokenKind::Float(num),
-                        Err(_) => {
-                            return Some(Err(format!(
-                                "Invalid float literal '{}' at line {}, column {}",
-                                number_str, line, column
-                            )));
-                        }
-                    }
-                } else {
-                    match number_str.parse::<i64>() {
-                        Ok(num) => TokenKind::Integer(num),
-                        Err(_) => {
-                            return Some(Err(format!(
-                                "Invalid integer literal '{}' at line {}, column {}",
-                                number_str, line, column
-                            )));
-                        }
-                    }
-                }
-            }
+            '"' if self.rest().starts_with("\"\"") => match self.lex_multiline_string(start) {
+                Ok(value) => TokenKind::MultilineString(value),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            '\'' => match self.lex_char(start) {
+                Ok(value) => TokenKind::Char(value),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            '"' => match self.lex_string(start) {
+                Ok(value) => TokenKind::String(value),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            'r' if self.at_raw_string() => match self.lex_raw_string(start) {
+                Ok(value) => TokenKind::RawString(value),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            'f' if self.peek() == Some('"') => match self.lex_interpolated_string(start) {
+                Ok(parts) => TokenKind::InterpolatedString(parts),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            'b' if self.peek() == Some('"') => match self.lex_byte_string(start) {
+                Ok(value) => TokenKind::ByteString(value),
+                Err(e) => return Some(self.error_token(start, e)),
+            },
+            c if c.is_ascii_digit() => match self.lex_number(start) {
+                Ok(kind) => kind,
+                Err(e) => return Some(self.error_token(start, e)),
+            },
             c if is_xid_start(c) || c == '_' => {
                 let mut ident_str = c.to_string();
                 ident_str.push_str(self.eat_while(|ch| is_xid_continue(ch) || ch == '_'));
@@ -200,15 +313,17 @@ impl Iterator for LexerCursor<'_> {
                 }
             }
             _ => {
-                return Some(Err(format!(
-                    "Unexpected character '{}' at line {}, column {}",
-                    c, line, column
-                )));
+                let diagnostic = Diagnostic::error(
+                    codes::UNEXPECTED_CHARACTER,
+                    format!("Unexpected character '{}'", c),
+                    self.span_from(start),
+                );
+                return Some(self.error_token(start, diagnostic.into()));
             }
         };
-        Some(Ok(Token {
+        Some(Token {
             kind: tokenskind,
-            pos: (line, column),
-        }))
+            span: self.span_from(start),
+        })
     }
 }
@@ -0,0 +1,202 @@
+use unicode_ident::is_xid_start;
+
+use super::{
+    LexerCursor,
+    types::{FloatSuffix, IntSuffix, TokenKind},
+};
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    span::Span,
+};
+
+impl LexerCursor<'_> {
+    /// Lexes a number literal whose first digit, already consumed, is at byte
+    /// `start`: `0x`/`0o`/`0b` integers, decimal integers and floats with an
+    /// optional fraction and exponent, `_` separators anywhere after the first
+    /// digit, and a type suffix such as `u8` or `f32`.
+    pub(super) fn lex_number(&mut self, start: usize) -> Result<TokenKind, Box<Diagnostic>> {
+        let radix = match (&self.src[start..self.position], self.peek()) {
+            ("0", Some('x')) => 16,
+            ("0", Some('o')) => 8,
+            ("0", Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.bump();
+            return self.lex_radix_integer(start, radix);
+        }
+
+        self.eat_while(|c| c.is_ascii_digit() || c == '_');
+        let mut is_float = false;
+        // `1.5` and `1.` are floats, but in `1.foo()` or `1..2` the dot is not ours.
+        if self.peek() == Some('.') {
+            let after_dot = self.rest()[1..].chars().next();
+            if !after_dot.is_some_and(|c| is_xid_start(c) || c == '_' || c == '.') {
+                self.bump();
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+                is_float = true;
+            }
+        }
+        if self.at_exponent() {
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            is_float = true;
+        }
+        let digits_end = self.position;
+        let suffix = self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        let digits = self.src[start..digits_end].replace('_', "");
+
+        if let Some(suffix) = FloatSuffix::parse(suffix) {
+            return self.float_literal(start, &digits, Some(suffix));
+        }
+        if suffix.is_empty() && is_float {
+            return self.float_literal(start, &digits, None);
+        }
+        let suffix = match (suffix.is_empty(), IntSuffix::parse(suffix)) {
+            (true, _) => None,
+            (false, Some(suffix)) if !is_float => Some(suffix),
+            (false, Some(_)) => {
+                return Err(Diagnostic::error(
+                    codes::INVALID_NUMBER,
+                    format!("Integer suffix '{}' on float literal", suffix),
+                    self.span_from(start),
+                )
+                .with_help("float literals take an 'f32' or 'f64' suffix")
+                .into());
+            }
+            (false, None) => {
+                return Err(Diagnostic::error(
+                    codes::INVALID_NUMBER,
+                    format!("Invalid suffix '{}' for number literal", suffix),
+                    self.span_from(start),
+                )
+                .with_help("valid suffixes are i8, i16, i32, i64, u8, u16, u32, u64, f32 and f64")
+                .into());
+            }
+        };
+        self.integer_literal(start, &digits, 10, suffix)
+    }
+
+    /// Whether an exponent such as `e9`, `E-3` or `e+1_0` follows.
+    fn at_exponent(&self) -> bool {
+        let mut chars = self.rest().chars();
+        if !matches!(chars.next(), Some('e' | 'E')) {
+            return false;
+        }
+        match chars.next() {
+            Some('+' | '-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    /// Lexes the rest of a `0x`, `0o` or `0b` integer after its prefix.
+    fn lex_radix_integer(
+        &mut self,
+        start: usize,
+        radix: u32,
+    ) -> Result<TokenKind, Box<Diagnostic>> {
+        let body_start = self.position;
+        let body = self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        let digits_len = body
+            .find(|c: char| !(c.is_digit(radix) || c == '_'))
+            .unwrap_or(body.len());
+        let (digits, suffix) = body.split_at(digits_len);
+        let suffix = if suffix.is_empty() {
+            None
+        } else if let Some(suffix) = IntSuffix::parse(suffix) {
+            Some(suffix)
+        } else if suffix.starts_with(|c: char| c.is_ascii_hexdigit()) {
+            let name = match radix {
+                16 => "hexadecimal",
+                8 => "octal",
+                _ => "binary",
+            };
+            let digit_start = body_start + digits_len;
+            return Err(Diagnostic::error(
+                codes::INVALID_NUMBER,
+                format!(
+                    "Invalid digit '{}' in {} literal",
+                    suffix.chars().next().unwrap(),
+                    name
+                ),
+                self.span_from(start),
+            )
+            .with_label(
+                Span::new(self.file, digit_start, digit_start + 1),
+                "not a valid digit",
+            )
+            .into());
+        } else {
+            return Err(Diagnostic::error(
+                codes::INVALID_NUMBER,
+                format!("Invalid suffix '{}' for number literal", suffix),
+                self.span_from(start),
+            )
+            .into());
+        };
+        let digits = digits.replace('_', "");
+        if digits.is_empty() {
+            return Err(Diagnostic::error(
+                codes::INVALID_NUMBER,
+                "Missing digits after the base prefix",
+                self.span_from(start),
+            )
+            .into());
+        }
+        self.integer_literal(start, &digits, radix, suffix)
+    }
+
+    fn integer_literal(
+        &self,
+        start: usize,
+        digits: &str,
+        radix: u32,
+        suffix: Option<IntSuffix>,
+    ) -> Result<TokenKind, Box<Diagnostic>> {
+        // Unsuffixed integers default to i64.
+        let ty = suffix.unwrap_or(IntSuffix::I64);
+        match u64::from_str_radix(digits, radix) {
+            Ok(value) if value <= ty.max() => Ok(TokenKind::Integer { value, suffix }),
+            _ => Err(Diagnostic::error(
+                codes::NUMBER_OUT_OF_RANGE,
+                format!("Integer literal out of range for {}", ty.name()),
+                self.span_from(start),
+            )
+            .with_note(format!("the largest {} is {}", ty.name(), ty.max()))
+            .into()),
+        }
+    }
+
+    fn float_literal(
+        &self,
+        start: usize,
+        digits: &str,
+        suffix: Option<FloatSuffix>,
+    ) -> Result<TokenKind, Box<Diagnostic>> {
+        let value = digits.parse::<f64>().map_err(|_| {
+            Diagnostic::error(
+                codes::INVALID_NUMBER,
+                "Invalid float literal",
+                self.span_from(start),
+            )
+        })?;
+        let overflows = match suffix {
+            Some(FloatSuffix::F32) => (value as f32).is_infinite(),
+            _ => value.is_infinite(),
+        };
+        if overflows {
+            let ty = suffix.unwrap_or(FloatSuffix::F64);
+            return Err(Diagnostic::error(
+                codes::NUMBER_OUT_OF_RANGE,
+                format!("Float literal out of range for {}", ty.name()),
+                self.span_from(start),
+            )
+            .into());
+        }
+        Ok(TokenKind::Float { value, suffix })
+    }
+}
@@ -0,0 +1,501 @@
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use super::{
+    LexerCursor,
+    types::{StringPart, Token, TokenKind},
+};
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    span::Span,
+};
+
+impl LexerCursor<'_> {
+    /// Lexes the rest of a string literal whose opening `"` is at byte
+    /// `start`, decoding escape sequences. The whole literal is consumed even
+    /// when it contains a bad escape, so lexing resumes after the closing `"`.
+    pub(super) fn lex_string(&mut self, start: usize) -> Result<String, Box<Diagnostic>> {
+        self.lex_quoted(start, false)
+    }
+
+    /// Lexes the rest of a `b"..."` literal. Only ASCII characters and
+    /// escapes are allowed, and `\x` escapes may produce any byte.
+    pub(super) fn lex_byte_string(&mut self, start: usize) -> Result<Vec<u8>, Box<Diagnostic>> {
+        self.bump();
+        let value = self.lex_quoted(start, true)?;
+        Ok(value.chars().map(|c| c as u8).collect())
+    }
+
+    /// Lexes the rest of a character literal whose opening `'` is at byte
+    /// `start`. A `'` followed by an identifier and no closing quote would be
+    /// a lifetime or label, which the language does not have (yet).
+    pub(super) fn lex_char(&mut self, start: usize) -> Result<char, Box<Diagnostic>> {
+        let mut chars = self.rest().chars();
+        if let (Some(c), next) = (chars.next(), chars.next())
+            && (is_xid_start(c) || c == '_')
+            && next != Some('\'')
+        {
+            let name = self.eat_while(|c| is_xid_continue(c) || c == '_');
+            if self.peek() == Some('\'') {
+                self.bump();
+                return Err(Diagnostic::error(
+                    codes::INVALID_CHAR_LITERAL,
+                    "Character literal may only contain one character",
+                    self.span_from(start),
+                )
+                .with_suggestion(
+                    "use a string literal for text",
+                    self.span_from(start),
+                    format!("\"{}\"", name),
+                )
+                .into());
+            }
+            return Err(Diagnostic::error(
+                codes::UNSUPPORTED_LIFETIME,
+                format!("Lifetimes and labels are not supported: '{}", name),
+                self.span_from(start),
+            )
+            .into());
+        }
+        let unterminated = |lexer: &Self| {
+            Diagnostic::error(
+                codes::UNTERMINATED_LITERAL,
+                "Unterminated character literal",
+                lexer.span_from(start),
+            )
+        };
+        let value = match self.bump() {
+            None | Some('\n') => return Err(unterminated(self).into()),
+            Some('\'') => {
+                return Err(Diagnostic::error(
+                    codes::INVALID_CHAR_LITERAL,
+                    "Empty character literal",
+                    self.span_from(start),
+                )
+                .into());
+            }
+            Some('\\') => self.lex_escape(false),
+            Some(c) => Ok(c),
+        };
+        // A bad escape is reported as such, after skipping the rest of the
+        // literal so lexing resumes past it.
+        if let Err(error) = value {
+            if !self.eat_through_quote() {
+                return Err(unterminated(self).into());
+            }
+            return Err(error);
+        }
+        if self.peek() != Some('\'') {
+            if !self.eat_through_quote() {
+                return Err(unterminated(self).into());
+            }
+            let text = &self.src[start + 1..self.position - 1];
+            return Err(Diagnostic::error(
+                codes::INVALID_CHAR_LITERAL,
+                "Character literal may only contain one character",
+                self.span_from(start),
+            )
+            .with_suggestion(
+                "use a string literal for text",
+                self.span_from(start),
+                format!("\"{}\"", text),
+            )
+            .into());
+        }
+        self.bump();
+        value
+    }
+
+    /// Consumes everything up to and including the next `'`, if there is
+    /// one before the end of the line.
+    fn eat_through_quote(&mut self) -> bool {
+        let rest_of_line = self.rest().split('\n').next().unwrap_or_default();
+        let Some(end) = rest_of_line.find('\'') else {
+            return false;
+        };
+        for _ in rest_of_line[..=end].chars() {
+            self.bump();
+        }
+        true
+    }
+
+    fn lex_quoted(&mut self, start: usize, byte: bool) -> Result<String, Box<Diagnostic>> {
+        let mut value = String::new();
+        let mut error = None;
+        loop {
+            let Some(c) = self.bump() else {
+                return Err(self.unterminated_string(start).into());
+            };
+            match c {
+                '"' => break,
+                '\\' => match self.lex_escape(byte) {
+                    Ok(ch) => value.push(ch),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                },
+                c if byte && !c.is_ascii() => {
+                    error.get_or_insert(
+                        Diagnostic::error(
+                            codes::NON_ASCII_BYTE,
+                            format!("Non-ASCII character '{}' in byte string", c),
+                            Span::new(self.file, self.position - c.len_utf8(), self.position),
+                        )
+                        .into(),
+                    );
+                }
+                c => value.push(c),
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(value),
+        }
+    }
+
+    fn unterminated_string(&self, start: usize) -> Diagnostic {
+        Diagnostic::error(
+            codes::UNTERMINATED_LITERAL,
+            "Unterminated string literal",
+            Span::new(self.file, start, start + 1),
+        )
+        .with_label(
+            Span::new(self.file, self.position, self.position),
+            "reached the end of the file here",
+        )
+    }
+
+    /// Decodes the escape sequence following a `\`.
+    fn lex_escape(&mut self, byte: bool) -> Result<char, Box<Diagnostic>> {
+        let start = self.position - 1;
+        let ch = match self.peek() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('x') => {
+                self.bump();
+                return self.lex_hex_escape(start, byte);
+            }
+            Some('u') if byte => {
+                self.bump();
+                return Err(Diagnostic::error(
+                    codes::INVALID_ESCAPE,
+                    "Unicode escape in byte string",
+                    self.span_from(start),
+                )
+                .with_help("use '\\x' escapes for bytes")
+                .into());
+            }
+            Some('u') => {
+                self.bump();
+                return self.lex_unicode_escape(start);
+            }
+            Some(other) => {
+                return Err(Diagnostic::error(
+                    codes::INVALID_ESCAPE,
+                    format!("Unknown escape sequence '\\{}'", other.escape_default()),
+                    Span::new(self.file, start, self.position + other.len_utf8()),
+                )
+                .into());
+            }
+            // The caller reports this as an unterminated literal.
+            None => {
+                return Err(Diagnostic::error(
+                    codes::INVALID_ESCAPE,
+                    "Missing escape sequence",
+                    self.span_from(start),
+                )
+                .into());
+            }
+        };
+        self.bump();
+        Ok(ch)
+    }
+
+    /// Decodes the two hex digits of a `\xNN` escape starting at byte `start`.
+    /// Outside byte strings the value must be ASCII.
+    fn lex_hex_escape(&mut self, start: usize, byte: bool) -> Result<char, Box<Diagnostic>> {
+        let rest = self.rest();
+        let digits = rest
+            .char_indices()
+            .take_while(|(i, c)| *i < 2 && c.is_ascii_hexdigit())
+            .count();
+        if digits != 2 {
+            return Err(Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                "Hex escape must have exactly 2 hex digits",
+                self.span_from(start),
+            )
+            .into());
+        }
+        self.bump();
+        self.bump();
+        let value = u8::from_str_radix(&rest[..2], 16).expect("only hex digits were consumed");
+        if !byte && !value.is_ascii() {
+            return Err(Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                format!(
+                    "Hex escape '\\x{}' out of range (must be at most '\\x7F')",
+                    &rest[..2]
+                ),
+                self.span_from(start),
+            )
+            .with_help("use a '\\u{..}' escape for non-ASCII characters")
+            .into());
+        }
+        Ok(char::from(value))
+    }
+
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape starting at byte `start`.
+    fn lex_unicode_escape(&mut self, start: usize) -> Result<char, Box<Diagnostic>> {
+        if self.peek() != Some('{') {
+            return Err(Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                "Expected '{' after '\\u'",
+                self.span_from(start),
+            )
+            .into());
+        }
+        self.bump();
+        let digits = self.eat_while(|c| c.is_ascii_hexdigit());
+        if self.peek() != Some('}') {
+            return Err(Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                "Unterminated unicode escape",
+                self.span_from(start),
+            )
+            .into());
+        }
+        self.bump();
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                "Unicode escape must have 1 to 6 hex digits",
+                self.span_from(start),
+            )
+            .into());
+        }
+        let value = u32::from_str_radix(digits, 16).expect("only hex digits were consumed");
+        char::from_u32(value).ok_or_else(|| {
+            Diagnostic::error(
+                codes::INVALID_ESCAPE,
+                format!("Invalid unicode scalar value '{}'", digits),
+                self.span_from(start),
+            )
+            .into()
+        })
+    }
+
+    /// Lexes the rest of an `f"..."` literal after its `f`. Text outside braces
+    /// is decoded like an ordinary string, `{{` and `}}` stand for literal
+    /// braces, and every `{expression}` is lexed into its own token stream.
+    pub(super) fn lex_interpolated_string(
+        &mut self,
+        start: usize,
+    ) -> Result<Vec<StringPart<Token>>, Box<Diagnostic>> {
+        self.bump();
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut error = None;
+        loop {
+            let Some(c) = self.bump() else {
+                return Err(self.unterminated_string(start).into());
+            };
+            match c {
+                '"' => break,
+                '\\' => match self.lex_escape(false) {
+                    Ok(ch) => literal.push(ch),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                },
+                '{' | '}' if self.peek() == Some(c) => {
+                    self.bump();
+                    literal.push(c);
+                }
+                '}' => {
+                    error.get_or_insert(
+                        Diagnostic::error(
+                            codes::INVALID_INTERPOLATION,
+                            "Unmatched '}' in interpolated string",
+                            self.span_from(self.position - 1),
+                        )
+                        .with_suggestion(
+                            "write '}}' for a literal brace",
+                            self.span_from(self.position - 1),
+                            "}}",
+                        )
+                        .into(),
+                    );
+                }
+                '{' => {
+                    if !literal.is_empty() {
+                        parts.push(StringPart::Literal(std::mem::take(&mut literal)));
+                    }
+                    let open = self.position - 1;
+                    parts.push(StringPart::Hole(self.lex_hole(open)?));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(StringPart::Literal(literal));
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(parts),
+        }
+    }
+
+    /// Lexes the tokens of an interpolation hole whose `{` is at byte `open`,
+    /// up to and consuming its closing `}`.
+    fn lex_hole(&mut self, open: usize) -> Result<Vec<Token>, Box<Diagnostic>> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        loop {
+            let Some(token) = self.next() else {
+                return Err(Diagnostic::error(
+                    codes::INVALID_INTERPOLATION,
+                    "Unclosed interpolation",
+                    Span::new(self.file, open, open + 1),
+                )
+                .into());
+            };
+            match token.kind {
+                TokenKind::LCurly => depth += 1,
+                TokenKind::RCurly if depth == 0 => return Ok(tokens),
+                TokenKind::RCurly => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+    }
+
+    /// Whether the `r` just consumed starts a raw string: `r"`, `r#"`, `r##"`, ...
+    pub(super) fn at_raw_string(&self) -> bool {
+        self.rest().trim_start_matches('#').starts_with('"')
+    }
+
+    /// Lexes the rest of a raw string after its `r`. Nothing is escaped; the
+    /// literal ends at the first `"` followed by as many `#` as it opened with.
+    pub(super) fn lex_raw_string(&mut self, start: usize) -> Result<String, Box<Diagnostic>> {
+        let hashes = self.eat_while(|c| c == '#').len();
+        self.bump();
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let Some(end) = self.rest().find(&closing) else {
+            let opening = self.span_from(start);
+            while self.bump().is_some() {}
+            return Err(Diagnostic::error(
+                codes::UNTERMINATED_LITERAL,
+                "Unterminated raw string literal",
+                opening,
+            )
+            .with_help(format!("close it with '{}'", closing))
+            .into());
+        };
+        let value = self.rest()[..end].to_string();
+        for _ in value.chars().chain(closing.chars()) {
+            self.bump();
+        }
+        Ok(value)
+    }
+
+    /// Lexes the rest of a `"""` string whose first quote is at byte `start`.
+    ///
+    /// Escapes are decoded as in ordinary strings. A line break right after the
+    /// opening quotes is dropped, as is the line holding the closing quotes when
+    /// it contains nothing else, and the indentation shared by the remaining
+    /// non-blank lines and the closing line is stripped from every line.
+    pub(super) fn lex_multiline_string(&mut self, start: usize) -> Result<String, Box<Diagnostic>> {
+        self.bump();
+        self.bump();
+        if self.rest().starts_with("\r\n") {
+            self.bump();
+        }
+        if self.rest().starts_with('\n') {
+            self.bump();
+        }
+        let Some(body) = multiline_body(self.rest()) else {
+            while self.bump().is_some() {}
+            return Err(Diagnostic::error(
+                codes::UNTERMINATED_LITERAL,
+                "Unterminated multi-line string literal",
+                Span::new(self.file, start, start + 3),
+            )
+            .into());
+        };
+        let last_line = body.rsplit('\n').next().unwrap_or_default();
+        let closing_on_own_line = body.contains('\n') && last_line.trim().is_empty();
+        let indent = body
+            .split('\n')
+            .filter(|l| !l.trim().is_empty())
+            .chain(closing_on_own_line.then_some(last_line))
+            .map(|l| l.chars().take_while(|c| *c == ' ' || *c == '\t').count())
+            .min()
+            .unwrap_or(0);
+
+        let mut value = String::new();
+        let mut error = None;
+        let mut at_line_start = true;
+        loop {
+            if at_line_start {
+                for _ in 0..indent {
+                    if !matches!(self.peek(), Some(' ' | '\t')) {
+                        break;
+                    }
+                    self.bump();
+                }
+                at_line_start = false;
+            }
+            if self.rest().starts_with("\"\"\"") {
+                self.bump();
+                self.bump();
+                self.bump();
+                break;
+            }
+            match self.bump().expect("the closing quotes were found above") {
+                '\\' => match self.lex_escape(false) {
+                    Ok(ch) => value.push(ch),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                },
+                '\n' => {
+                    value.push('\n');
+                    at_line_start = true;
+                }
+                c => value.push(c),
+            }
+        }
+        if closing_on_own_line {
+            let end = value
+                .rfind('\n')
+                .expect("closing line follows a line break");
+            value.truncate(end);
+            if value.ends_with('\r') {
+                value.pop();
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(value),
+        }
+    }
+}
+
+/// The source of a `"""` string up to (not including) its closing quotes,
+/// skipping over escaped characters.
+fn multiline_body(rest: &str) -> Option<&str> {
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if rest[i..].starts_with("\"\"\"") {
+            return Some(&rest[..i]);
+        }
+    }
+    None
+}
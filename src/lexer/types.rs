@@ -1,7 +1,9 @@
+use crate::{diagnostic::Diagnostic, span::Span};
+
 #[derive(Debug)]
 pub struct Token {
     pub kind: TokenKind,
-    pub pos: (usize, usize),
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -22,6 +24,9 @@ pub enum TokenKind {
 
     Comma,
     Colon,
+    PathSep,
+    Dot,
+    FatArrow,
     SemiColon,
 
     LParen,
@@ -31,15 +36,122 @@ pub enum TokenKind {
     LBracket,
     RBracket,
 
+    /// The text after `///` or `//!`, up to the end of the line.
+    DocComment(String),
+
     Identifier(String),
+    Char(char),
     String(String),
-    Integer(i64),
-    Float(f64),
+    RawString(String),
+    ByteString(Vec<u8>),
+    MultilineString(String),
+    InterpolatedString(Vec<StringPart<Token>>),
+    Integer {
+        value: u64,
+        suffix: Option<IntSuffix>,
+    },
+    Float {
+        value: f64,
+        suffix: Option<FloatSuffix>,
+    },
 
     Op(Op),
+    /// Malformed input and the diagnostic explaining what is wrong with it.
+    Error {
+        text: String,
+        diagnostic: Box<Diagnostic>,
+    },
 }
 
+/// A piece of an `f"..."` string: either literal text or the tokens of an
+/// embedded `{expression}`. `T` is a token or a token tree depending on the stage.
 #[derive(PartialEq, Debug)]
+pub enum StringPart<T> {
+    Literal(String),
+    Hole(Vec<T>),
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum IntSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntSuffix {
+    pub fn parse(suffix: &str) -> Option<Self> {
+        let suffix = match suffix {
+            "i8" => IntSuffix::I8,
+            "i16" => IntSuffix::I16,
+            "i32" => IntSuffix::I32,
+            "i64" => IntSuffix::I64,
+            "u8" => IntSuffix::U8,
+            "u16" => IntSuffix::U16,
+            "u32" => IntSuffix::U32,
+            "u64" => IntSuffix::U64,
+            _ => return None,
+        };
+        Some(suffix)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntSuffix::I8 => "i8",
+            IntSuffix::I16 => "i16",
+            IntSuffix::I32 => "i32",
+            IntSuffix::I64 => "i64",
+            IntSuffix::U8 => "u8",
+            IntSuffix::U16 => "u16",
+            IntSuffix::U32 => "u32",
+            IntSuffix::U64 => "u64",
+        }
+    }
+
+    /// The largest literal of this type. Literals are never negative; `-`
+    /// is a separate prefix operator.
+    pub fn max(self) -> u64 {
+        match self {
+            IntSuffix::I8 => i8::MAX as u64,
+            IntSuffix::I16 => i16::MAX as u64,
+            IntSuffix::I32 => i32::MAX as u64,
+            IntSuffix::I64 => i64::MAX as u64,
+            IntSuffix::U8 => u8::MAX as u64,
+            IntSuffix::U16 => u16::MAX as u64,
+            IntSuffix::U32 => u32::MAX as u64,
+            IntSuffix::U64 => u64::MAX,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+impl FloatSuffix {
+    pub fn parse(suffix: &str) -> Option<Self> {
+        match suffix {
+            "f32" => Some(FloatSuffix::F32),
+            "f64" => Some(FloatSuffix::F64),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatSuffix::F32 => "f32",
+            FloatSuffix::F64 => "f64",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Op {
     Pipe,
 
@@ -67,30 +179,73 @@ pub enum Op {
     BitNot,
     ShiftLeft,
     ShiftRight,
-}
 
-pub struct StringParser {
-    skip_next: bool,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
+    BitAndAssign,
+    BitOrAssign,
+    BitXorAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
 }
 
-impl StringParser {
-    pub fn new() -> Self {
-        StringParser { skip_next: false }
-    }
-
-    pub fn condition(&mut self, ch: char) -> bool {
-        if self.skip_next {
-            self.skip_next = false;
-            return true;
+impl Op {
+    /// How the operator is written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Op::Pipe => "|>",
+            Op::Plus => "+",
+            Op::Minus => "-",
+            Op::Multiply => "*",
+            Op::Divide => "/",
+            Op::Modulo => "%",
+            Op::Equal => "=",
+            Op::EqualTo => "==",
+            Op::NotEqual => "!=",
+            Op::GreaterThan => ">",
+            Op::LessThan => "<",
+            Op::GreaterThanOrEqual => ">=",
+            Op::LessThanOrEqual => "<=",
+            Op::And => "&&",
+            Op::Or => "||",
+            Op::Not => "!",
+            Op::BitAnd => "&",
+            Op::BitOr => "|",
+            Op::BitXor => "^",
+            Op::BitNot => "~",
+            Op::ShiftLeft => "<<",
+            Op::ShiftRight => ">>",
+            Op::PlusAssign => "+=",
+            Op::MinusAssign => "-=",
+            Op::MultiplyAssign => "*=",
+            Op::DivideAssign => "/=",
+            Op::ModuloAssign => "%=",
+            Op::BitAndAssign => "&=",
+            Op::BitOrAssign => "|=",
+            Op::BitXorAssign => "^=",
+            Op::ShiftLeftAssign => "<<=",
+            Op::ShiftRightAssign => ">>=",
         }
+    }
 
-        match ch {
-            '\\' => {
-                self.skip_next = true;
-                true
-            }
-            '"' => false,
-            _ => true,
-        }
+    /// `=` and the compound assignments such as `+=`.
+    pub fn is_assignment(&self) -> bool {
+        matches!(
+            self,
+            Op::Equal
+                | Op::PlusAssign
+                | Op::MinusAssign
+                | Op::MultiplyAssign
+                | Op::DivideAssign
+                | Op::ModuloAssign
+                | Op::BitAndAssign
+                | Op::BitOrAssign
+                | Op::BitXorAssign
+                | Op::ShiftLeftAssign
+                | Op::ShiftRightAssign
+        )
     }
 }
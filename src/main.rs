@@ -1,33 +1,70 @@
+mod diagnostic;
 mod lexer;
 mod parser;
+mod passes;
+mod span;
 mod tokentree;
-use clap::Parser;
+use clap::{ColorChoice, Parser};
+use diagnostic::{Emitter, ErrorFormat};
 use parser::Statement;
 
 #[derive(Parser)]
 struct Args {
     /// Input file
     input: String,
+    /// When to colour error output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// How to write errors
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    match run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
 
-fn run() -> Result<(), String> {
+/// Returns whether the input compiled without errors.
+fn run() -> Result<bool, String> {
     let args = Args::parse();
-    let input =
-        std::fs::read_to_string(args.input).map_err(|_| "Failed to read input file".to_string())?;
-    let lexer = lexer::LexerCursor::new(&input);
-    let tokens = lexer.collect::<Result<Vec<_>, String>>()?;
-    let tokenstream = tokentree::TokenTree::parse_from_tokens(&mut tokens.into_iter())?;
+    let input = std::fs::read_to_string(&args.input)
+        .map_err(|_| "error: Failed to read input file".to_string())?;
+    let colour = match args.color {
+        ColorChoice::Auto => Emitter::detect_colour(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    let mut emitter = Emitter::new(args.error_format, colour);
+    let mut source_map = span::SourceMap::new();
+    let file = source_map.add_file(args.input, input);
+    let tokens: Vec<_> = lexer::LexerCursor::new(&source_map.file(file).src, file).collect();
+    lexer::errors(&tokens)
+        .iter()
+        .for_each(|e| emitter.emit(e, &source_map));
+    let (tokenstream, errors) =
+        tokentree::TokenTree::parse_from_tokens(tokens, source_map.file(file));
+    errors.iter().for_each(|e| emitter.emit(e, &source_map));
     let mut parser = parser::Parser::new(tokenstream);
-    let ast: Vec<Statement> = parser.parse_statements().unwrap();
-    parser.errors.iter().for_each(|e| println!("{}", e));
+    let mut ast: Vec<Statement> = parser.parse_statements().unwrap();
+    parser
+        .errors
+        .iter()
+        .for_each(|e| emitter.emit(e, &source_map));
+    passes::desugar::Desugarer::desugar(&mut ast)
+        .iter()
+        .for_each(|e| emitter.emit(e, &source_map));
+    passes::mutability::MutabilityChecker::check(&ast)
+        .iter()
+        .for_each(|e| emitter.emit(e, &source_map));
     println!("{:#?}", ast);
 
-    Ok(())
+    emitter.finish();
+    Ok(!emitter.has_errors())
 }
@@ -1,13 +1,79 @@
-use types::ast::{ExpressionStatement, Literal, LiteralExpr, ReturnStatement};
+use types::{
+    Group, Precedence,
+    ast::{
+        ArrayType, AssignExpression, BlockExpression, CallExpression, EnumStatement, ErrorNode,
+        ExpressionStatement, Field, FieldExpression, FnStatement, FnType, IfExpression,
+        IndexExpression, InfixExpression, InterpolatedExpression, InterpolatedPart,
+        LambdaExpression, Literal, LiteralExpr, NamedType, Param, PathExpression, PathSegment,
+        PipeExpression, PrefixExpression, ReturnStatement, StructStatement, TupleType, Type,
+        Variant, VariantKind, WhileExpression,
+    },
+};
 pub use types::{
     Parser,
-    ast::{BeStatement, Expression, Ident, Pos, Statement},
+    ast::{BeStatement, Expression, Ident, Spanned, Statement},
 };
 
-use crate::{lexer::types::Op, tokentree::TokenTreeKind};
+use crate::{
+    diagnostic::{Diagnostic, codes, suggest},
+    lexer::types::{Op, StringPart},
+    span::Span,
+    tokentree::{GroupMode, TokenTree, TokenTreeKind},
+};
 
 pub mod types;
 
+/// Keywords that can start a statement.
+const STATEMENT_KEYWORDS: &[&str] = &["be", "fn", "return", "struct", "enum", "if", "while"];
+
+/// Keywords from other languages, with the keyword meant by them here.
+const KEYWORD_ALIASES: &[(&str, &str)] = &[
+    ("let", "be"),
+    ("var", "be"),
+    ("val", "be"),
+    ("const", "be"),
+    ("def", "fn"),
+    ("func", "fn"),
+    ("function", "fn"),
+    ("class", "struct"),
+    ("mutable", "mut"),
+];
+
+/// Whether `word` is plausibly what someone would write when they mean
+/// `keyword`: its counterpart in another language, or a misspelling.
+fn could_mean(word: &str, keyword: &str) -> bool {
+    KEYWORD_ALIASES
+        .iter()
+        .any(|&(alias, meant)| alias == word && meant == keyword)
+        || suggest::closest(word, [keyword]).is_some()
+}
+
+/// Whether a token of `kind` can come straight after an expression in the
+/// same statement.
+fn continues_expression(kind: &TokenTreeKind) -> bool {
+    matches!(
+        kind,
+        TokenTreeKind::Op(_)
+            | TokenTreeKind::Group {
+                mode: GroupMode::Parens | GroupMode::Bracket,
+                ..
+            }
+            | TokenTreeKind::Dot
+            | TokenTreeKind::PathSep
+            | TokenTreeKind::SemiColon
+            | TokenTreeKind::Comma
+            | TokenTreeKind::Colon
+            | TokenTreeKind::FatArrow
+            | TokenTreeKind::Error { .. }
+    )
+}
+
+/// What a leading `fn` turned out to introduce.
+enum FnOrLambda {
+    Item(FnStatement),
+    Lambda(LambdaExpression),
+}
+
 #[macro_export]
 macro_rules! peek_is {
     ($iter:expr, $($pat:tt)+) => {
@@ -19,28 +85,244 @@ impl Parser {
     pub fn parse_statements(&mut self) -> Option<Vec<Statement>> {
         let mut statements = Vec::new();
         loop {
+            self.skip_misplaced_doc_comments();
             if self.tokenstream.peek().is_none() {
                 break;
             }
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
-            }
+            statements.push(self.parse_statement());
         }
         Some(statements)
     }
 
-    fn parse_statement(&mut self) -> Option<Statement> {
+    /// Parses one statement. A statement that fails to parse is skipped up to
+    /// the next synchronization point and kept as a [`Statement::Error`].
+    fn parse_statement(&mut self) -> Statement {
+        self.recovering = false;
+        let start = self.peek_span();
+        if !self.fix_unknown_keyword() {
+            return self.recover_statement(start);
+        }
+        match self.try_parse_statement() {
+            Some(stmt) => stmt,
+            None => self.recover_statement(start),
+        }
+    }
+
+    fn try_parse_statement(&mut self) -> Option<Statement> {
         let peeked = self.peek_token()?;
         let stmt = match peeked.kind {
             TokenTreeKind::Be => Statement::Be(self.parse_be_statement()?),
             TokenTreeKind::Return => Statement::Return(self.parse_return_statement()?),
+            TokenTreeKind::Fn => match self.parse_fn()? {
+                FnOrLambda::Item(fn_stmt) => Statement::Fn(fn_stmt),
+                FnOrLambda::Lambda(lambda) => {
+                    let start = lambda.span;
+                    let expr = Expression::Lambda(lambda);
+                    Statement::Expression(self.finish_expression_statement(expr, start))
+                }
+            },
+            TokenTreeKind::Struct => Statement::Struct(self.parse_struct_statement()?),
+            TokenTreeKind::Enum => Statement::Enum(self.parse_enum_statement()?),
+            TokenTreeKind::DocComment(_) => self.parse_documented_item()?,
             _ => Statement::Expression(self.parse_expression_statement()?),
         };
         Some(stmt)
     }
 
+    /// Looks for a statement that starts with a word where a keyword belongs,
+    /// as in `let x = 1;`, since no statement starts with two words in a row.
+    /// If the words are close to the keywords that belong there, they are
+    /// reported along with those keywords and then replaced by them, so the
+    /// statement parses as intended. Otherwise the first stray word is
+    /// reported and `false` is returned, as the statement can't be parsed.
+    fn fix_unknown_keyword(&mut self) -> bool {
+        let tokens = self.tokenstream.lookahead_mut();
+        let kind = |i: usize| tokens.get(i).map(|tok| &tok.kind);
+        let is_word = |i: usize| matches!(kind(i), Some(TokenTreeKind::Identifier(_)));
+        let binds = |i: usize| {
+            matches!(
+                kind(i),
+                Some(TokenTreeKind::Op(Op::Equal) | TokenTreeKind::Colon)
+            )
+        };
+        // Where the unknown words start, what they should be and why.
+        let (at, keywords, help): (usize, Vec<&str>, &str) = match kind(0) {
+            // `be mutable x = ..`
+            Some(TokenTreeKind::Be) if is_word(1) && is_word(2) => (
+                1,
+                vec!["mut"],
+                "mutable variables are declared with 'be mut'",
+            ),
+            Some(TokenTreeKind::Identifier(word)) => {
+                if matches!(kind(1), Some(TokenTreeKind::Mut)) || is_word(1) && binds(2) {
+                    // `let x = ..` or `let mut x = ..`
+                    (0, vec!["be"], "variables are declared with 'be'")
+                } else if is_word(1) && is_word(2) && binds(3) {
+                    // `var mutable x = ..`
+                    (
+                        0,
+                        vec!["be", "mut"],
+                        "mutable variables are declared with 'be mut'",
+                    )
+                } else if kind(1).is_some_and(|kind| !continues_expression(kind)) {
+                    let keyword = KEYWORD_ALIASES
+                        .iter()
+                        .find(|(alias, _)| alias == word)
+                        .map(|&(_, keyword)| keyword)
+                        .or_else(|| suggest::closest(word, STATEMENT_KEYWORDS.iter().copied()));
+                    match keyword {
+                        Some(keyword) => (0, vec![keyword], "a keyword with a similar name exists"),
+                        None => return true,
+                    }
+                } else {
+                    return true;
+                }
+            }
+            _ => return true,
+        };
+        let words = &mut tokens[at..at + keywords.len()];
+        let stray = words.iter().zip(&keywords).find(|(token, keyword)| {
+            matches!(&token.kind, TokenTreeKind::Identifier(word) if !could_mean(word, keyword))
+        });
+        if let Some((token, _)) = stray {
+            let error = Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                format!("Unexpected {}", token.kind.describe()),
+                token.span,
+            );
+            self.report(error);
+            return false;
+        }
+        let span = words[0].span.to(words[words.len() - 1].span);
+        let mut text = Vec::new();
+        for (token, &keyword) in words.iter_mut().zip(&keywords) {
+            if let TokenTreeKind::Identifier(word) = &token.kind {
+                text.push(word.clone());
+            }
+            token.kind = match keyword {
+                "be" => TokenTreeKind::Be,
+                "mut" => TokenTreeKind::Mut,
+                "fn" => TokenTreeKind::Fn,
+                "return" => TokenTreeKind::Return,
+                "struct" => TokenTreeKind::Struct,
+                "enum" => TokenTreeKind::Enum,
+                "if" => TokenTreeKind::If,
+                "while" => TokenTreeKind::While,
+                _ => unreachable!("not a keyword: {}", keyword),
+            };
+        }
+        // The statement goes on to parse, so this doesn't count as the
+        // error that starts recovery.
+        self.errors.push(
+            Diagnostic::error(
+                codes::UNKNOWN_KEYWORD,
+                format!("Unknown keyword '{}'", text.join(" ")),
+                span,
+            )
+            .with_suggestion(help, span, keywords.join(" ")),
+        );
+        true
+    }
+
+    /// The span of the next token, or the empty span where it is missing.
+    fn peek_span(&mut self) -> Span {
+        match self.tokenstream.peek() {
+            Some(token) => token.span,
+            None => self.end_span(),
+        }
+    }
+
+    fn recover_statement(&mut self, start: Span) -> Statement {
+        self.synchronize(start);
+        Statement::Error(ErrorNode {
+            span: self.span_from(start),
+        })
+    }
+
+    /// Like [`Parser::recover_statement`], for when the statement is still
+    /// worth keeping around the bad expression, such as to keep a binding
+    /// declared.
+    fn recover_expression(&mut self, start: Span) -> Expression {
+        self.synchronize(start);
+        Expression::Error(ErrorNode {
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_doc_comments(&mut self) -> Vec<String> {
+        let mut docs = Vec::new();
+        while let Some(TokenTreeKind::DocComment(doc)) = self
+            .tokenstream
+            .next_if(|tok| matches!(tok.kind, TokenTreeKind::DocComment(_)))
+            .map(|tok| tok.kind)
+        {
+            docs.push(doc);
+        }
+        docs
+    }
+
+    /// Skips a run of doc comments that isn't followed by an item they could
+    /// document, with a warning, so that what follows parses as usual.
+    fn skip_misplaced_doc_comments(&mut self) {
+        let tokens = self.tokenstream.lookahead_mut();
+        let count = tokens
+            .iter()
+            .take_while(|tok| matches!(tok.kind, TokenTreeKind::DocComment(_)))
+            .count();
+        if count == 0 {
+            return;
+        }
+        let message = match tokens.get(count).map(|tok| &tok.kind) {
+            // A `fn` may turn out to be a lambda, which `parse_documented_item` handles.
+            Some(TokenTreeKind::Fn | TokenTreeKind::Struct | TokenTreeKind::Enum) => return,
+            Some(_) => "Doc comment does not document an item",
+            None => "Doc comment does not document anything",
+        };
+        let span = tokens[0].span.to(tokens[count - 1].span);
+        for _ in 0..count {
+            self.tokenstream.next();
+        }
+        self.errors.push(
+            Diagnostic::warning(codes::MISPLACED_DOC_COMMENT, message, span)
+                .with_note("doc comments only document 'fn', 'struct' and 'enum' items"),
+        );
+    }
+
+    /// Parses the item following a run of doc comments and attaches them to it.
+    fn parse_documented_item(&mut self) -> Option<Statement> {
+        let start = self.tokenstream.peek()?.span;
+        let docs = self.parse_doc_comments();
+        let mut stmt = match self.tokenstream.peek().map(|tok| &tok.kind) {
+            Some(TokenTreeKind::Fn) => match self.parse_fn()? {
+                FnOrLambda::Item(fn_stmt) => Statement::Fn(fn_stmt),
+                FnOrLambda::Lambda(lambda) => {
+                    self.errors.push(
+                        Diagnostic::warning(
+                            codes::MISPLACED_DOC_COMMENT,
+                            "Doc comment cannot document a lambda expression",
+                            start,
+                        )
+                        .with_note("doc comments only document 'fn', 'struct' and 'enum' items"),
+                    );
+                    let expr = Expression::Lambda(lambda);
+                    Statement::Expression(self.finish_expression_statement(expr, start))
+                }
+            },
+            Some(TokenTreeKind::Struct) => Statement::Struct(self.parse_struct_statement()?),
+            Some(TokenTreeKind::Enum) => Statement::Enum(self.parse_enum_statement()?),
+            _ => unreachable!("misplaced doc comments are skipped before statements"),
+        };
+        match &mut stmt {
+            Statement::Fn(fn_stmt) => fn_stmt.docs = docs,
+            Statement::Struct(struct_stmt) => struct_stmt.docs = docs,
+            Statement::Enum(enum_stmt) => enum_stmt.docs = docs,
+            _ => {}
+        }
+        Some(stmt)
+    }
+
     fn parse_be_statement(&mut self) -> Option<BeStatement> {
-        let pos = self.tokenstream.next().unwrap().pos;
+        let start = self.tokenstream.next().unwrap().span;
         let is_mut = if peek_is!(self.tokenstream, TokenTreeKind::Mut) {
             self.tokenstream.next();
             true
@@ -48,106 +330,921 @@ impl Parser {
             false
         };
         if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
-            self.errors.push(format!(
-                "Expected identifier after 'be' at line {}, column {}",
-                pos.0, pos.1
-            ));
+            if !self.at_lexical_error() {
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    "Expected identifier after 'be'",
+                    start,
+                ));
+            }
             return None;
         }
         let ident: Ident = self.parse_ident()?;
-        if !peek_is!(self.tokenstream, TokenTreeKind::Op(ref op) if op == &Op::Equal) {
-            self.errors.push(format!(
-                "Expected '=' after identifier at line {}, column {}",
-                ident.pos.0, ident.pos.1
-            ));
-            return None;
-        } else {
+        let ty = if peek_is!(self.tokenstream, TokenTreeKind::Colon) {
             self.tokenstream.next();
-        }
-        let value: Expression = self.parse_expression()?;
-        if !peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
-            self.errors.push(format!(
-                "Expected ';' after expression at line {}, column {}",
-                value.pos().0,
-                value.pos().1
-            ));
-            return None;
+            Some(self.parse_type()?)
         } else {
-            self.tokenstream.next();
+            None
+        };
+        let has_equal = self
+            .tokenstream
+            .next_if(|tok| matches!(tok.kind, TokenTreeKind::Op(Op::Equal)))
+            .is_some();
+        if !has_equal {
+            self.report(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                "Expected '=' after identifier",
+                ident.span,
+            ));
         }
+        let value_start = self.peek_span();
+        let value = if has_equal {
+            self.parse_expression(Precedence::Lowest)
+        } else {
+            None
+        };
+        let value = match value {
+            Some(value) => {
+                // `be x = { .. }` may omit the `;` just like a block statement.
+                if peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
+                    self.tokenstream.next();
+                } else if !value.is_block_like() {
+                    self.error_missing_semicolon(value.span());
+                }
+                value
+            }
+            // Keep the binding so that its later uses still resolve.
+            None => self.recover_expression(value_start),
+        };
         Some(BeStatement {
             ident,
+            ty,
             value,
             is_mut,
-            pos,
+            span: self.span_from(start),
         })
     }
 
     fn parse_return_statement(&mut self) -> Option<ReturnStatement> {
-        let pos = self.tokenstream.next()?.pos;
-        let expr: Expression = self.parse_expression()?;
-        if !peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
-            self.errors.push(format!(
-                "Expected ';' after expression at line {}, column {}",
-                expr.pos().0,
-                expr.pos().1
+        let start = self.tokenstream.next()?.span;
+        let expr_start = self.peek_span();
+        let expr = match self.parse_expression(Precedence::Lowest) {
+            Some(expr) => {
+                if peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
+                    self.tokenstream.next();
+                } else {
+                    self.error_missing_semicolon(expr.span());
+                }
+                expr
+            }
+            None => self.recover_expression(expr_start),
+        };
+        Some(ReturnStatement {
+            expr,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses either a named function item or, when `fn` is directly
+    /// followed by a parameter list, a lambda expression.
+    fn parse_fn(&mut self) -> Option<FnOrLambda> {
+        let start = self.tokenstream.next().unwrap().span;
+        if peek_is!(
+            self.tokenstream,
+            TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            }
+        ) {
+            return Some(FnOrLambda::Lambda(self.parse_lambda_rest(start)?));
+        }
+        if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+            self.report(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                "Expected function name or '(' after 'fn'",
+                start,
             ));
             return None;
+        }
+        Some(FnOrLambda::Item(self.parse_fn_statement_rest(start)?))
+    }
+
+    fn parse_fn_statement_rest(&mut self, start: Span) -> Option<FnStatement> {
+        let ident = self.parse_ident()?;
+        let params = self.parse_params()?;
+        let return_type = if peek_is!(self.tokenstream, TokenTreeKind::Colon) {
+            self.tokenstream.next();
+            Some(self.parse_type()?)
         } else {
+            None
+        };
+        if !peek_is!(
+            self.tokenstream,
+            TokenTreeKind::Group {
+                mode: GroupMode::Curly,
+                ..
+            }
+        ) {
+            self.error_expected("'{' to start function body");
+            return None;
+        }
+        let body = self.parse_block_expression()?;
+        Some(FnStatement {
+            docs: Vec::new(),
+            ident,
+            params,
+            return_type,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_params(&mut self) -> Option<Vec<Param>> {
+        let group = self.expect_group(GroupMode::Parens, "'(' to start parameter list")?;
+        self.parse_comma_separated(group, Self::parse_param)
+    }
+
+    fn parse_param(&mut self) -> Option<Param> {
+        let ident = self.parse_ident()?;
+        self.expect(TokenTreeKind::Colon, "':' after parameter name")?;
+        let ty = self.parse_type()?;
+        Some(Param {
+            span: ident.span.to(ty.span()),
+            ident,
+            ty,
+        })
+    }
+
+    fn parse_struct_statement(&mut self) -> Option<StructStatement> {
+        let start = self.tokenstream.next().unwrap().span;
+        if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+            if !self.at_lexical_error() {
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    "Expected struct name after 'struct'",
+                    start,
+                ));
+            }
+            return None;
+        }
+        let ident = self.parse_ident()?;
+        let group = self.expect_group(GroupMode::Curly, "'{' to start struct fields")?;
+        let fields = self.parse_comma_separated(group, Self::parse_field)?;
+        Some(StructStatement {
+            docs: Vec::new(),
+            ident,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_field(&mut self) -> Option<Field> {
+        let docs = self.parse_doc_comments();
+        let ident = self.parse_ident()?;
+        self.expect(TokenTreeKind::Colon, "':' after field name")?;
+        let ty = self.parse_type()?;
+        Some(Field {
+            docs,
+            span: ident.span.to(ty.span()),
+            ident,
+            ty,
+        })
+    }
+
+    fn parse_enum_statement(&mut self) -> Option<EnumStatement> {
+        let start = self.tokenstream.next().unwrap().span;
+        if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+            if !self.at_lexical_error() {
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    "Expected enum name after 'enum'",
+                    start,
+                ));
+            }
+            return None;
+        }
+        let ident = self.parse_ident()?;
+        let group = self.expect_group(GroupMode::Curly, "'{' to start enum variants")?;
+        let variants = self.parse_comma_separated(group, Self::parse_variant)?;
+        Some(EnumStatement {
+            docs: Vec::new(),
+            ident,
+            variants,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_variant(&mut self) -> Option<Variant> {
+        let docs = self.parse_doc_comments();
+        let ident = self.parse_ident()?;
+        let kind = if peek_is!(
+            self.tokenstream,
+            TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            }
+        ) {
+            let group = self.expect_group(GroupMode::Parens, "'('")?;
+            VariantKind::Tuple(self.parse_comma_separated(group, Self::parse_type)?)
+        } else if peek_is!(
+            self.tokenstream,
+            TokenTreeKind::Group {
+                mode: GroupMode::Curly,
+                ..
+            }
+        ) {
+            let group = self.expect_group(GroupMode::Curly, "'{'")?;
+            VariantKind::Struct(self.parse_comma_separated(group, Self::parse_field)?)
+        } else {
+            VariantKind::Unit
+        };
+        Some(Variant {
+            docs,
+            span: self.span_from(ident.span),
+            ident,
+            kind,
+        })
+    }
+
+    fn parse_type(&mut self) -> Option<Type> {
+        let peeked = self.peek_token()?;
+        let ty = match &peeked.kind {
+            TokenTreeKind::Identifier(_) => Type::Named(self.parse_named_type()?),
+            TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            } => self.parse_tuple_type()?,
+            TokenTreeKind::Group {
+                mode: GroupMode::Bracket,
+                ..
+            } => Type::Array(self.parse_array_type()?),
+            TokenTreeKind::Fn => Type::Fn(self.parse_fn_type()?),
+            _ => {
+                self.error_expected("type");
+                return None;
+            }
+        };
+        Some(ty)
+    }
+
+    fn parse_named_type(&mut self) -> Option<NamedType> {
+        let first = self.parse_ident()?;
+        let start = first.span;
+        let mut segments = vec![PathSegment {
+            span: first.span,
+            ident: first,
+            generics: Vec::new(),
+        }];
+        while peek_is!(self.tokenstream, TokenTreeKind::PathSep) {
             self.tokenstream.next();
+            if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+                self.error_expected("identifier after '::'");
+                return None;
+            }
+            let ident = self.parse_ident()?;
+            segments.push(PathSegment {
+                span: ident.span,
+                ident,
+                generics: Vec::new(),
+            });
         }
-        Some(ReturnStatement { expr, pos })
+        // In type position `<` always opens a generic argument list, never a comparison.
+        if peek_is!(self.tokenstream, TokenTreeKind::Op(Op::LessThan)) {
+            let generics = self.parse_generic_args()?;
+            let segment = segments.last_mut().unwrap();
+            segment.generics = generics;
+            segment.span = segment.span.to(self.tokenstream.prev_span);
+        }
+        Some(NamedType {
+            segments,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `<A, B>`, starting at the `<`.
+    fn parse_generic_args(&mut self) -> Option<Vec<Type>> {
+        self.tokenstream.next();
+        let mut generics = Vec::new();
+        loop {
+            if self.eat_closing_angle() {
+                break;
+            }
+            generics.push(self.parse_type()?);
+            if self.eat_closing_angle() {
+                break;
+            }
+            self.expect(TokenTreeKind::Comma, "',' or '>' in generic arguments")?;
+        }
+        Some(generics)
+    }
+
+    /// Consumes a `>` closing a generic argument list. Tokens the lexer glued
+    /// onto the `>` (as in `Vec<Vec<T>>` or `Vec<T>= ..`) are split off and
+    /// left in the stream.
+    fn eat_closing_angle(&mut self) -> bool {
+        let Some(token) = self.tokenstream.peek_mut() else {
+            return false;
+        };
+        let rest = match token.kind {
+            TokenTreeKind::Op(Op::GreaterThan) => None,
+            TokenTreeKind::Op(Op::ShiftRight) => Some(Op::GreaterThan),
+            TokenTreeKind::Op(Op::GreaterThanOrEqual) => Some(Op::Equal),
+            TokenTreeKind::Op(Op::ShiftRightAssign) => Some(Op::GreaterThanOrEqual),
+            _ => return false,
+        };
+        match rest {
+            Some(op) => {
+                let angle = Span {
+                    end: token.span.start + 1,
+                    ..token.span
+                };
+                token.kind = TokenTreeKind::Op(op);
+                token.span.start = angle.end;
+                self.tokenstream.prev_span = angle;
+            }
+            None => {
+                self.tokenstream.next();
+            }
+        }
+        true
+    }
+
+    fn parse_tuple_type(&mut self) -> Option<Type> {
+        let group = self.expect_group(GroupMode::Parens, "'('")?;
+        let span = group.span;
+        let trailing_comma = matches!(
+            group.children.last(),
+            Some(TokenTree {
+                kind: TokenTreeKind::Comma,
+                ..
+            })
+        );
+        let mut elements = self.parse_comma_separated(group, Self::parse_type)?;
+        // `(T)` is just a parenthesized `T`; a one-element tuple is written `(T,)`.
+        if elements.len() == 1 && !trailing_comma {
+            return elements.pop();
+        }
+        Some(Type::Tuple(TupleType { elements, span }))
+    }
+
+    fn parse_array_type(&mut self) -> Option<ArrayType> {
+        let Group {
+            children,
+            open,
+            span,
+        } = self.expect_group(GroupMode::Bracket, "'['")?;
+        self.within_group(children, open, |parser| {
+            let element = parser.parse_type()?;
+            let len = if peek_is!(parser.tokenstream, TokenTreeKind::SemiColon) {
+                parser.tokenstream.next();
+                Some(Box::new(parser.parse_expression(Precedence::Lowest)?))
+            } else {
+                None
+            };
+            if parser.tokenstream.peek().is_some() {
+                parser.error_expected("']' after array type");
+                return None;
+            }
+            Some(ArrayType {
+                element: Box::new(element),
+                len,
+                span,
+            })
+        })
+    }
+
+    fn parse_fn_type(&mut self) -> Option<FnType> {
+        let start = self.tokenstream.next().unwrap().span;
+        let group = self.expect_group(GroupMode::Parens, "'(' after 'fn'")?;
+        let params = self.parse_comma_separated(group, Self::parse_type)?;
+        let return_type = if peek_is!(self.tokenstream, TokenTreeKind::Colon) {
+            self.tokenstream.next();
+            Some(Box::new(self.parse_type()?))
+        } else {
+            None
+        };
+        Some(FnType {
+            params,
+            return_type,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses the children of `group` as a list of `parse_item`, separated by
+    /// commas and with an optional trailing comma.
+    fn parse_comma_separated<T>(
+        &mut self,
+        group: Group,
+        mut parse_item: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        self.within_group(group.children, group.open, |parser| {
+            let mut items = Vec::new();
+            while parser.tokenstream.peek().is_some() {
+                items.push(parse_item(parser)?);
+                if parser.tokenstream.peek().is_some() {
+                    parser.expect(TokenTreeKind::Comma, "','")?;
+                }
+            }
+            Some(items)
+        })
     }
 
     fn parse_expression_statement(&mut self) -> Option<ExpressionStatement> {
-        let pos = self.tokenstream.peek()?.pos;
-        let expr: Expression = self.parse_expression()?;
-        if !peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
-            self.errors.push(format!(
-                "Expected ';' after expression at line {}, column {}",
-                expr.pos().0,
-                expr.pos().1
-            ));
-            return None;
+        let start = self.tokenstream.peek()?.span;
+        let expr: Expression = self.parse_statement_expression()?;
+        Some(self.finish_expression_statement(expr, start))
+    }
+
+    /// Parses the expression of an expression statement. A statement that
+    /// starts with a block-like expression ends with its closing `}`, so
+    /// `if c { .. } -1;` is two statements rather than a subtraction.
+    fn parse_statement_expression(&mut self) -> Option<Expression> {
+        if peek_is!(
+            self.tokenstream,
+            TokenTreeKind::If
+                | TokenTreeKind::While
+                | TokenTreeKind::Group {
+                    mode: GroupMode::Curly,
+                    ..
+                }
+        ) {
+            self.parse_prefix()
         } else {
+            self.parse_expression(Precedence::Lowest)
+        }
+    }
+
+    fn finish_expression_statement(
+        &mut self,
+        expr: Expression,
+        start: Span,
+    ) -> ExpressionStatement {
+        if peek_is!(self.tokenstream, TokenTreeKind::SemiColon) {
             self.tokenstream.next();
+        } else if !expr.is_block_like() {
+            self.error_missing_semicolon(expr.span());
+        }
+        ExpressionStatement {
+            expr,
+            span: self.span_from(start),
         }
-        Some(ExpressionStatement { expr, pos })
     }
 
-    fn parse_expression(&mut self) -> Option<Expression> {
+    fn parse_block_expression(&mut self) -> Option<BlockExpression> {
+        let Group {
+            children,
+            open,
+            span,
+        } = self.expect_group(GroupMode::Curly, "'{'")?;
+        self.within_group(children, open, |parser| {
+            let mut statements = Vec::new();
+            let mut tail = None;
+            loop {
+                parser.recovering = false;
+                parser.skip_misplaced_doc_comments();
+                let Some(start) = parser.tokenstream.peek().map(|tok| tok.span) else {
+                    break;
+                };
+                if !parser.fix_unknown_keyword() {
+                    statements.push(parser.recover_statement(start));
+                    continue;
+                }
+                let peeked = parser.tokenstream.peek().unwrap();
+                if matches!(
+                    peeked.kind,
+                    TokenTreeKind::Be
+                        | TokenTreeKind::Return
+                        | TokenTreeKind::Struct
+                        | TokenTreeKind::Enum
+                        | TokenTreeKind::DocComment(_)
+                ) {
+                    statements.push(parser.parse_statement());
+                    continue;
+                }
+                let expr = if peek_is!(parser.tokenstream, TokenTreeKind::Fn) {
+                    match parser.parse_fn() {
+                        Some(FnOrLambda::Item(fn_stmt)) => {
+                            statements.push(Statement::Fn(fn_stmt));
+                            continue;
+                        }
+                        Some(FnOrLambda::Lambda(lambda)) => Some(Expression::Lambda(lambda)),
+                        None => None,
+                    }
+                } else {
+                    parser.parse_statement_expression()
+                };
+                let Some(expr) = expr else {
+                    statements.push(parser.recover_statement(start));
+                    continue;
+                };
+                // An expression running up to the closing `}` is the block's value.
+                if parser.tokenstream.peek().is_none() {
+                    tail = Some(Box::new(expr));
+                    break;
+                }
+                let stmt = parser.finish_expression_statement(expr, start);
+                statements.push(Statement::Expression(stmt));
+            }
+            Some(BlockExpression {
+                statements,
+                tail,
+                span,
+            })
+        })
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Option<Expression> {
+        let mut left = self.parse_prefix()?;
+        while self.peek_precedence() > precedence {
+            left = self.parse_infix(left)?;
+        }
+        Some(left)
+    }
+
+    fn peek_precedence(&mut self) -> Precedence {
+        match self.tokenstream.peek().map(|tok| &tok.kind) {
+            Some(TokenTreeKind::Op(op)) => Precedence::infix(op).unwrap_or(Precedence::Lowest),
+            Some(TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            }) => Precedence::Call,
+            Some(TokenTreeKind::Group {
+                mode: GroupMode::Bracket,
+                ..
+            })
+            | Some(TokenTreeKind::Dot) => Precedence::Index,
+            // Binds tightest so that `1 @ 2` fails at the bad token itself.
+            Some(TokenTreeKind::Error { .. }) => Precedence::Index,
+            _ => Precedence::Lowest,
+        }
+    }
+
+    fn parse_infix(&mut self, left: Expression) -> Option<Expression> {
+        let expr = match &self.tokenstream.peek()?.kind {
+            TokenTreeKind::Op(op) if op.is_assignment() => {
+                Expression::Assign(self.parse_assign_expression(left)?)
+            }
+            TokenTreeKind::Op(Op::Pipe) => Expression::Pipe(self.parse_pipe_expression(left)?),
+            TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            } => Expression::Call(self.parse_call_expression(left)?),
+            TokenTreeKind::Group {
+                mode: GroupMode::Bracket,
+                ..
+            } => Expression::Index(self.parse_index_expression(left)?),
+            TokenTreeKind::Dot => Expression::Field(self.parse_field_expression(left)?),
+            // The lexer has already reported it.
+            TokenTreeKind::Error { .. } => {
+                self.recovering = true;
+                return None;
+            }
+            _ => Expression::Infix(self.parse_infix_expression(left)?),
+        };
+        Some(expr)
+    }
+
+    fn parse_prefix(&mut self) -> Option<Expression> {
         let peeked = self.peek_token()?;
         let expr = match &peeked.kind {
-            TokenTreeKind::Identifier(_) => Expression::Ident(self.parse_ident()?),
-            TokenTreeKind::Integer(_)
-            | TokenTreeKind::Float(_)
+            TokenTreeKind::Identifier(_) => {
+                let ident = self.parse_ident()?;
+                if peek_is!(self.tokenstream, TokenTreeKind::PathSep) {
+                    Expression::Path(self.parse_path_expression(ident)?)
+                } else {
+                    Expression::Ident(ident)
+                }
+            }
+            TokenTreeKind::Integer { .. }
+            | TokenTreeKind::Float { .. }
             | TokenTreeKind::Boolean(_)
-            | TokenTreeKind::String(_) => Expression::Literal(self.parse_literal()?),
+            | TokenTreeKind::Char(_)
+            | TokenTreeKind::String(_)
+            | TokenTreeKind::RawString(_)
+            | TokenTreeKind::ByteString(_)
+            | TokenTreeKind::MultilineString(_) => Expression::Literal(self.parse_literal()?),
+            TokenTreeKind::Op(Op::Minus | Op::Not | Op::BitNot) => {
+                Expression::Prefix(self.parse_prefix_expression()?)
+            }
+            TokenTreeKind::Group {
+                mode: GroupMode::Parens,
+                ..
+            } => self.parse_grouped_expression()?,
+            TokenTreeKind::Group {
+                mode: GroupMode::Curly,
+                ..
+            } => Expression::Block(self.parse_block_expression()?),
+            TokenTreeKind::InterpolatedString(_) => {
+                Expression::Interpolated(self.parse_interpolated_expression()?)
+            }
+            TokenTreeKind::If => Expression::If(self.parse_if_expression()?),
+            TokenTreeKind::Fn => Expression::Lambda(self.parse_lambda_expression()?),
+            TokenTreeKind::While => Expression::While(self.parse_while_expression()?),
+            // The lexer has already reported it.
+            TokenTreeKind::Error { .. } => {
+                self.recovering = true;
+                return None;
+            }
             _ => {
-                let (peeked_string, line, col) =
-                    (format!("{:?}", peeked.kind), peeked.pos.0, peeked.pos.1);
-                self.errors.push(format!(
-                    "Unexpected token '{}' at line {}, column {}",
-                    peeked_string, line, col
+                let (found, span) = (peeked.kind.describe(), peeked.span);
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    format!("Unexpected {}", found),
+                    span,
                 ));
-                self.tokenstream.next();
                 return None;
             }
         };
         Some(expr)
     }
 
+    /// Parses the rest of a path after its first segment. Generic arguments
+    /// use the turbofish form `::<T>` so they cannot be mistaken for `<`.
+    fn parse_path_expression(&mut self, first: Ident) -> Option<PathExpression> {
+        let start = first.span;
+        let mut segments = vec![PathSegment {
+            span: first.span,
+            ident: first,
+            generics: Vec::new(),
+        }];
+        while peek_is!(self.tokenstream, TokenTreeKind::PathSep) {
+            self.tokenstream.next();
+            if peek_is!(self.tokenstream, TokenTreeKind::Op(Op::LessThan)) {
+                let generics = self.parse_generic_args()?;
+                let segment = segments.last_mut().unwrap();
+                segment.generics = generics;
+                segment.span = segment.span.to(self.tokenstream.prev_span);
+                continue;
+            }
+            if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+                self.error_expected("identifier or '<' after '::'");
+                return None;
+            }
+            let ident = self.parse_ident()?;
+            segments.push(PathSegment {
+                span: ident.span,
+                ident,
+                generics: Vec::new(),
+            });
+        }
+        Some(PathExpression {
+            segments,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_prefix_expression(&mut self) -> Option<PrefixExpression> {
+        let token = self.tokenstream.next()?;
+        let TokenTreeKind::Op(op) = token.kind else {
+            unreachable!("prefix expression must start with an operator");
+        };
+        let right = self.parse_expression(Precedence::Prefix)?;
+        Some(PrefixExpression {
+            op,
+            span: token.span.to(right.span()),
+            right: Box::new(right),
+        })
+    }
+
+    fn parse_infix_expression(&mut self, left: Expression) -> Option<InfixExpression> {
+        let token = self.tokenstream.next()?;
+        let TokenTreeKind::Op(op) = token.kind else {
+            unreachable!("infix expression must continue with an operator");
+        };
+        let precedence = Precedence::infix(&op)?;
+        // Parsing the right operand at the operator's own precedence makes
+        // every binary operator left-associative.
+        let right = self.parse_expression(precedence)?;
+        Some(InfixExpression {
+            span: left.span().to(right.span()),
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        })
+    }
+
+    fn parse_if_expression(&mut self) -> Option<IfExpression> {
+        let start = self.tokenstream.next().unwrap().span;
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        let consequence = self.parse_block_expression()?;
+        let alternative = if peek_is!(self.tokenstream, TokenTreeKind::Else) {
+            self.tokenstream.next();
+            if peek_is!(self.tokenstream, TokenTreeKind::If) {
+                Some(Box::new(Expression::If(self.parse_if_expression()?)))
+            } else {
+                Some(Box::new(Expression::Block(self.parse_block_expression()?)))
+            }
+        } else {
+            None
+        };
+        Some(IfExpression {
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_while_expression(&mut self) -> Option<WhileExpression> {
+        let start = self.tokenstream.next().unwrap().span;
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        let body = self.parse_block_expression()?;
+        Some(WhileExpression {
+            condition: Box::new(condition),
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_pipe_expression(&mut self, left: Expression) -> Option<PipeExpression> {
+        self.tokenstream.next();
+        let right = self.parse_expression(Precedence::Pipe)?;
+        Some(PipeExpression {
+            span: left.span().to(right.span()),
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    fn parse_call_expression(&mut self, callee: Expression) -> Option<CallExpression> {
+        let group = self.expect_group(GroupMode::Parens, "'('")?;
+        let span = group.span;
+        let args = self
+            .parse_comma_separated(group, |parser| parser.parse_expression(Precedence::Lowest))?;
+        Some(CallExpression {
+            span: callee.span().to(span),
+            callee: Box::new(callee),
+            args,
+        })
+    }
+
+    fn parse_index_expression(&mut self, target: Expression) -> Option<IndexExpression> {
+        let Group {
+            children,
+            open,
+            span,
+        } = self.expect_group(GroupMode::Bracket, "'['")?;
+        let index = self.within_group(children, open, |parser| {
+            let index = parser.parse_expression(Precedence::Lowest)?;
+            if parser.tokenstream.peek().is_some() {
+                parser.error_expected("']' after index");
+                return None;
+            }
+            Some(index)
+        })?;
+        Some(IndexExpression {
+            span: target.span().to(span),
+            target: Box::new(target),
+            index: Box::new(index),
+        })
+    }
+
+    fn parse_field_expression(&mut self, target: Expression) -> Option<FieldExpression> {
+        let dot = self.tokenstream.next()?;
+        if !peek_is!(self.tokenstream, TokenTreeKind::Identifier(_)) {
+            if !self.at_lexical_error() {
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    "Expected field name after '.'",
+                    dot.span,
+                ));
+            }
+            return None;
+        }
+        let field = self.parse_ident()?;
+        Some(FieldExpression {
+            span: target.span().to(field.span),
+            target: Box::new(target),
+            field,
+        })
+    }
+
+    fn parse_assign_expression(&mut self, target: Expression) -> Option<AssignExpression> {
+        let token = self.tokenstream.next()?;
+        let TokenTreeKind::Op(op) = token.kind else {
+            unreachable!("assignment must continue with an operator");
+        };
+        if !target.is_place() {
+            self.errors.push(
+                Diagnostic::error(
+                    codes::INVALID_ASSIGNMENT_TARGET,
+                    "Invalid left-hand side of assignment",
+                    token.span,
+                )
+                .with_label(target.span(), "cannot be assigned to"),
+            );
+            return None;
+        }
+        // Assignment is right-associative: `a = b = c` assigns `b = c` to `a`.
+        let value = self.parse_expression(Precedence::Lowest)?;
+        Some(AssignExpression {
+            span: target.span().to(value.span()),
+            target: Box::new(target),
+            op,
+            value: Box::new(value),
+        })
+    }
+
+    fn parse_interpolated_expression(&mut self) -> Option<InterpolatedExpression> {
+        let token = self.tokenstream.next()?;
+        let TokenTreeKind::InterpolatedString(string_parts) = token.kind else {
+            unreachable!("interpolated expression must start with an interpolated string");
+        };
+        let mut parts = Vec::new();
+        for part in string_parts {
+            match part {
+                StringPart::Literal(text) => parts.push(InterpolatedPart::Literal(text)),
+                StringPart::Hole(children) if children.is_empty() => {
+                    self.report(Diagnostic::error(
+                        codes::INVALID_INTERPOLATION,
+                        "Empty interpolation in string",
+                        token.span,
+                    ));
+                    return None;
+                }
+                StringPart::Hole(children) => {
+                    let expr = self.within_group(children, token.span, |parser| {
+                        let expr = parser.parse_expression(Precedence::Lowest)?;
+                        if parser.tokenstream.peek().is_some() {
+                            parser.error_expected("'}' after interpolated expression");
+                            return None;
+                        }
+                        Some(expr)
+                    })?;
+                    parts.push(InterpolatedPart::Expr(expr));
+                }
+            }
+        }
+        Some(InterpolatedExpression {
+            parts,
+            span: token.span,
+        })
+    }
+
+    fn parse_lambda_expression(&mut self) -> Option<LambdaExpression> {
+        let start = self.tokenstream.next().unwrap().span;
+        self.parse_lambda_rest(start)
+    }
+
+    /// Parses a lambda after its `fn` keyword; the parameter list and return
+    /// type use the same grammar as named functions.
+    fn parse_lambda_rest(&mut self, start: Span) -> Option<LambdaExpression> {
+        let params = self.parse_params()?;
+        let return_type = if peek_is!(self.tokenstream, TokenTreeKind::Colon) {
+            self.tokenstream.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let body = if peek_is!(self.tokenstream, TokenTreeKind::FatArrow) {
+            self.tokenstream.next();
+            self.parse_expression(Precedence::Lowest)?
+        } else if peek_is!(
+            self.tokenstream,
+            TokenTreeKind::Group {
+                mode: GroupMode::Curly,
+                ..
+            }
+        ) {
+            Expression::Block(self.parse_block_expression()?)
+        } else {
+            self.error_expected("'=>' or '{' to start lambda body");
+            return None;
+        };
+        Some(LambdaExpression {
+            params,
+            return_type,
+            body: Box::new(body),
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_grouped_expression(&mut self) -> Option<Expression> {
+        let Group { children, open, .. } = self.expect_group(GroupMode::Parens, "'('")?;
+        self.within_group(children, open, |parser| {
+            let expr = parser.parse_expression(Precedence::Lowest);
+            if parser.tokenstream.peek().is_some() {
+                parser.error_expected("')' after expression");
+            }
+            expr
+        })
+    }
+
     fn parse_ident(&mut self) -> Option<Ident> {
+        if self.at_lexical_error() {
+            return None;
+        }
         let token = self.tokenstream.next()?;
         if let TokenTreeKind::Identifier(name) = &token.kind {
             Some(Ident {
                 name: name.to_string(),
-                pos: token.pos,
+                span: token.span,
             })
         } else {
-            self.errors.push(format!(
-                "Expected identifier, found '{:?}' at line {}, column {}",
-                token.kind, token.pos.0, token.pos.1
+            self.report(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                format!("Expected identifier, found {}", token.kind.describe()),
+                token.span,
             ));
             None
         }
@@ -156,21 +1253,32 @@ impl Parser {
     fn parse_literal(&mut self) -> Option<LiteralExpr> {
         let token = self.tokenstream.next()?;
         let literal = match &token.kind {
-            TokenTreeKind::Integer(value) => Literal::Int(*value),
-            TokenTreeKind::Float(value) => Literal::Float(*value),
+            TokenTreeKind::Integer { value, suffix } => Literal::Int {
+                value: *value,
+                suffix: *suffix,
+            },
+            TokenTreeKind::Float { value, suffix } => Literal::Float {
+                value: *value,
+                suffix: *suffix,
+            },
             TokenTreeKind::Boolean(value) => Literal::Bool(*value),
+            TokenTreeKind::Char(value) => Literal::Char(*value),
             TokenTreeKind::String(value) => Literal::Str(value.clone()),
+            TokenTreeKind::RawString(value) => Literal::RawStr(value.clone()),
+            TokenTreeKind::ByteString(value) => Literal::ByteStr(value.clone()),
+            TokenTreeKind::MultilineString(value) => Literal::MultilineStr(value.clone()),
             _ => {
-                self.errors.push(format!(
-                    "Expected literal, found '{:?}' at line {}, column {}",
-                    token.kind, token.pos.0, token.pos.1
+                self.report(Diagnostic::error(
+                    codes::UNEXPECTED_TOKEN,
+                    format!("Expected literal, found {}", token.kind.describe()),
+                    token.span,
                 ));
                 return None;
             }
         };
         Some(LiteralExpr {
             value: literal,
-            pos: token.pos,
+            span: token.span,
         })
     }
 }
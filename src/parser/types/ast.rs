@@ -1,7 +1,12 @@
 use derivative::Derivative;
 
-pub trait Pos {
-    fn pos(&self) -> (usize, usize);
+use crate::{
+    lexer::types::{FloatSuffix, IntSuffix, Op},
+    span::Span,
+};
+
+pub trait Spanned {
+    fn span(&self) -> Span;
 }
 
 #[derive(Debug)]
@@ -9,16 +14,22 @@ pub enum Statement {
     Be(BeStatement),
     Return(ReturnStatement),
     Expression(ExpressionStatement),
+    Fn(FnStatement),
+    Struct(StructStatement),
+    Enum(EnumStatement),
+    /// A statement that failed to parse and was skipped.
+    Error(ErrorNode),
 }
 
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct BeStatement {
     pub ident: Ident,
+    pub ty: Option<Type>,
     pub value: Expression,
     pub is_mut: bool,
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
 }
 
 #[derive(Derivative)]
@@ -26,7 +37,7 @@ pub struct BeStatement {
 pub struct ReturnStatement {
     pub expr: Expression,
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
 }
 
 #[derive(Derivative)]
@@ -34,13 +45,299 @@ pub struct ReturnStatement {
 pub struct ExpressionStatement {
     pub expr: Expression,
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FnStatement {
+    pub docs: Vec<String>,
+    pub ident: Ident,
+    pub params: Vec<Param>,
+    pub return_type: Option<Type>,
+    pub body: BlockExpression,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Param {
+    pub ident: Ident,
+    pub ty: Type,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct StructStatement {
+    pub docs: Vec<String>,
+    pub ident: Ident,
+    pub fields: Vec<Field>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Field {
+    pub docs: Vec<String>,
+    pub ident: Ident,
+    pub ty: Type,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EnumStatement {
+    pub docs: Vec<String>,
+    pub ident: Ident,
+    pub variants: Vec<Variant>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Variant {
+    pub docs: Vec<String>,
+    pub ident: Ident,
+    pub kind: VariantKind,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[allow(dead_code)] // only read through `Debug` until there is a backend
+#[derive(Debug)]
+pub enum VariantKind {
+    /// `Variant`
+    Unit,
+    /// `Variant(A, B)`
+    Tuple(Vec<Type>),
+    /// `Variant { field: A }`
+    Struct(Vec<Field>),
+}
+
+#[derive(Debug)]
+pub enum Type {
+    Named(NamedType),
+    Tuple(TupleType),
+    Array(ArrayType),
+    Fn(FnType),
+}
+
+/// `Name`, `Name<A, B>` or a qualified path such as `io::File` or
+/// `std::Vec<T>`. Only the last segment has generic arguments.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct NamedType {
+    pub segments: Vec<PathSegment>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `(A, B)`; the empty tuple `()` is the unit type.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TupleType {
+    pub elements: Vec<Type>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `[T]` or `[T; N]`
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ArrayType {
+    pub element: Box<Type>,
+    pub len: Option<Box<Expression>>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `fn(A, B): R`; a missing return type means unit.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FnType {
+    pub params: Vec<Type>,
+    pub return_type: Option<Box<Type>>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub enum Expression {
     Ident(Ident),
+    Path(PathExpression),
     Literal(LiteralExpr),
+    Prefix(PrefixExpression),
+    Infix(InfixExpression),
+    Block(BlockExpression),
+    If(IfExpression),
+    While(WhileExpression),
+    Assign(AssignExpression),
+    Pipe(PipeExpression),
+    Call(CallExpression),
+    Index(IndexExpression),
+    Field(FieldExpression),
+    Lambda(LambdaExpression),
+    Interpolated(InterpolatedExpression),
+    /// An expression that failed to parse and was skipped.
+    Error(ErrorNode),
+}
+
+impl Expression {
+    /// Whether the expression ends in a `}` and so may stand as a statement
+    /// without a terminating `;`.
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self,
+            Expression::Block(_) | Expression::If(_) | Expression::While(_)
+        )
+    }
+
+    /// Whether the expression denotes a memory location that can be assigned to.
+    pub fn is_place(&self) -> bool {
+        matches!(
+            self,
+            Expression::Ident(_) | Expression::Index(_) | Expression::Field(_)
+        )
+    }
+
+    /// Whether the expression is the `_` hole of a piped call.
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self, Expression::Ident(ident) if ident.name == "_")
+    }
+}
+
+/// `{ stmt; stmt; tail }`; the block evaluates to `tail`, or unit without one.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct BlockExpression {
+    pub statements: Vec<Statement>,
+    pub tail: Option<Box<Expression>>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `target = value`, or a compound assignment such as `target += value`
+/// when `op` is one of the `*Assign` operators.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct AssignExpression {
+    pub target: Box<Expression>,
+    pub op: Op,
+    pub value: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `left |> right`; rewritten into a plain call by the desugaring pass.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PipeExpression {
+    pub left: Box<Expression>,
+    pub right: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    pub args: Vec<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `target[index]`
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct IndexExpression {
+    pub target: Box<Expression>,
+    pub index: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `target.field`
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FieldExpression {
+    pub target: Box<Expression>,
+    pub field: Ident,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `a::b::<T>::c`, naming an enum variant, an associated function or an
+/// item inside a module.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PathExpression {
+    pub segments: Vec<PathSegment>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PathSegment {
+    pub ident: Ident,
+    pub generics: Vec<Type>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `fn(x: T): R => expr` or `fn(x: T): R { .. }`
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct LambdaExpression {
+    pub params: Vec<Param>,
+    pub return_type: Option<Type>,
+    pub body: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// `f"text {expr} text"`
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct InterpolatedExpression {
+    pub parts: Vec<InterpolatedPart>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[allow(dead_code)] // only read through `Debug` until there is a backend
+#[derive(Debug)]
+pub enum InterpolatedPart {
+    Literal(String),
+    Expr(Expression),
+}
+
+/// `if cond { .. } else ..`, where the `else` branch is either a block or
+/// another `if` for `else if` chains.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct IfExpression {
+    pub condition: Box<Expression>,
+    pub consequence: BlockExpression,
+    pub alternative: Option<Box<Expression>>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct WhileExpression {
+    pub condition: Box<Expression>,
+    pub body: BlockExpression,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
 }
 
 #[derive(Derivative)]
@@ -48,15 +345,26 @@ pub enum Expression {
 pub struct Ident {
     pub name: String,
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
 }
 
+#[allow(dead_code)] // only read through `Debug` until there is a backend
 #[derive(Debug)]
 pub enum Literal {
-    Int(i64),
-    Float(f64),
+    Int {
+        value: u64,
+        suffix: Option<IntSuffix>,
+    },
+    Float {
+        value: f64,
+        suffix: Option<FloatSuffix>,
+    },
     Bool(bool),
+    Char(char),
     Str(String),
+    RawStr(String),
+    ByteStr(Vec<u8>),
+    MultilineStr(String),
 }
 
 #[derive(Derivative)]
@@ -64,56 +372,261 @@ pub enum Literal {
 pub struct LiteralExpr {
     pub value: Literal,
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PrefixExpression {
+    pub op: Op,
+    pub right: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct InfixExpression {
+    pub left: Box<Expression>,
+    pub op: Op,
+    pub right: Box<Expression>,
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+/// Stands in for source the parser could not make sense of, so the rest of
+/// the tree survives. The error has already been reported.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ErrorNode {
+    #[derivative(Debug = "ignore")]
+    pub span: Span,
+}
+
+impl Spanned for Statement {
+    fn span(&self) -> Span {
+        match self {
+            Statement::Be(be_stmt) => be_stmt.span(),
+            Statement::Return(ret_stmt) => ret_stmt.span(),
+            Statement::Expression(expr) => expr.span(),
+            Statement::Fn(fn_stmt) => fn_stmt.span(),
+            Statement::Struct(struct_stmt) => struct_stmt.span(),
+            Statement::Enum(enum_stmt) => enum_stmt.span(),
+            Statement::Error(error) => error.span(),
+        }
+    }
+}
+
+impl Spanned for BeStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for ReturnStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for ExpressionStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for FnStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for Param {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for StructStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for Field {
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
-pub struct PrefixExpression {}
+impl Spanned for EnumStatement {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
 
-impl Pos for Statement {
-    fn pos(&self) -> (usize, usize) {
+impl Spanned for Variant {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for Type {
+    fn span(&self) -> Span {
         match self {
-            Statement::Be(be_stmt) => be_stmt.pos(),
-            Statement::Return(ret_stmt) => ret_stmt.pos(),
-            Statement::Expression(expr) => expr.pos(),
+            Type::Named(named) => named.span(),
+            Type::Tuple(tuple) => tuple.span(),
+            Type::Array(array) => array.span(),
+            Type::Fn(fn_type) => fn_type.span(),
         }
     }
 }
 
-impl Pos for BeStatement {
-    fn pos(&self) -> (usize, usize) {
-        self.pos
+impl Spanned for NamedType {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for TupleType {
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
-impl Pos for ReturnStatement {
-    fn pos(&self) -> (usize, usize) {
-        self.pos
+impl Spanned for ArrayType {
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
-impl Pos for ExpressionStatement {
-    fn pos(&self) -> (usize, usize) {
-        self.pos
+impl Spanned for FnType {
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
-impl Pos for Expression {
-    fn pos(&self) -> (usize, usize) {
+impl Spanned for Expression {
+    fn span(&self) -> Span {
         match self {
-            Expression::Ident(ident) => ident.pos(),
-            Expression::Literal(lit) => lit.pos(),
+            Expression::Ident(ident) => ident.span(),
+            Expression::Path(path) => path.span(),
+            Expression::Literal(lit) => lit.span(),
+            Expression::Prefix(prefix) => prefix.span(),
+            Expression::Infix(infix) => infix.span(),
+            Expression::Block(block) => block.span(),
+            Expression::If(if_expr) => if_expr.span(),
+            Expression::While(while_expr) => while_expr.span(),
+            Expression::Assign(assign) => assign.span(),
+            Expression::Pipe(pipe) => pipe.span(),
+            Expression::Call(call) => call.span(),
+            Expression::Index(index) => index.span(),
+            Expression::Field(field) => field.span(),
+            Expression::Lambda(lambda) => lambda.span(),
+            Expression::Interpolated(interpolated) => interpolated.span(),
+            Expression::Error(error) => error.span(),
         }
     }
 }
 
-impl Pos for Ident {
-    fn pos(&self) -> (usize, usize) {
-        self.pos
+impl Spanned for BlockExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for AssignExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for PipeExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for CallExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for IndexExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for FieldExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for PathExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for PathSegment {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for LambdaExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for InterpolatedExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for IfExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for WhileExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for Ident {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for LiteralExpr {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for PrefixExpression {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Spanned for InfixExpression {
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
-impl Pos for LiteralExpr {
-    fn pos(&self) -> (usize, usize) {
-        self.pos
+impl Spanned for ErrorNode {
+    fn span(&self) -> Span {
+        self.span
     }
 }
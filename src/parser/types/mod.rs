@@ -1,38 +1,288 @@
-use std::{iter::Peekable, vec::IntoIter};
+use std::vec::IntoIter;
 
-use crate::tokentree::TokenTree;
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    lexer::types::Op,
+    peek_is,
+    span::Span,
+    tokentree::{GroupMode, TokenTree, TokenTreeKind},
+};
 
 pub mod ast;
 
+/// A peekable stream of token trees that remembers the span of the last
+/// token it handed out, so nodes can be given a span ending there.
+pub(super) struct TokenCursor {
+    tokens: IntoIter<TokenTree>,
+    pub(super) prev_span: Span,
+}
+
+impl TokenCursor {
+    /// A cursor over `tokens` that behaves as if `prev_span` had just been
+    /// handed out, so errors before the first token point just past it.
+    fn new(tokens: Vec<TokenTree>, prev_span: Span) -> Self {
+        TokenCursor {
+            tokens: tokens.into_iter(),
+            prev_span,
+        }
+    }
+
+    pub(super) fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.as_slice().first()
+    }
+
+    pub(super) fn peek_mut(&mut self) -> Option<&mut TokenTree> {
+        self.tokens.as_mut_slice().first_mut()
+    }
+
+    /// All the tokens not yet handed out, for looking further ahead than
+    /// [`TokenCursor::peek`].
+    pub(super) fn lookahead_mut(&mut self) -> &mut [TokenTree] {
+        self.tokens.as_mut_slice()
+    }
+
+    pub(super) fn next_if(&mut self, func: impl FnOnce(&TokenTree) -> bool) -> Option<TokenTree> {
+        if self.peek().is_some_and(func) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for TokenCursor {
+    type Item = TokenTree;
+
+    fn next(&mut self) -> Option<TokenTree> {
+        let token = self.tokens.next()?;
+        self.prev_span = token.span;
+        Some(token)
+    }
+}
+
+/// A group consumed by [`Parser::expect_group`].
+pub(super) struct Group {
+    pub(super) children: Vec<TokenTree>,
+    /// The span of the opening delimiter.
+    pub(super) open: Span,
+    /// The span from the opening through the closing delimiter.
+    pub(super) span: Span,
+}
+
 pub struct Parser {
-    pub(super) tokenstream: Peekable<IntoIter<TokenTree>>,
-    pub errors: Vec<String>,
+    pub(super) tokenstream: TokenCursor,
+    pub errors: Vec<Diagnostic>,
+    /// Set once the current statement has gone wrong. Errors reported while
+    /// it is set are most likely knock-on effects of the first and are
+    /// dropped; it is cleared when the next statement starts.
+    pub(super) recovering: bool,
 }
 
 impl Parser {
     pub fn new(tokenstream: Vec<TokenTree>) -> Self {
         Self {
-            tokenstream: tokenstream.into_iter().peekable(),
+            tokenstream: TokenCursor::new(tokenstream, Span::default()),
             errors: Vec::new(),
+            recovering: false,
         }
     }
 
     pub fn peek_token(&mut self) -> Option<&TokenTree> {
         if self.tokenstream.peek().is_none() {
-            self.errors.push("Unexpected EOF".to_string());
+            self.report(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                "Unexpected end of input",
+                self.end_span(),
+            ));
             return None;
         }
         self.tokenstream.peek()
     }
+
+    /// Consumes the next token if it is `kind`, otherwise records an error
+    /// mentioning `expected` and leaves the token in place.
+    pub(super) fn expect(&mut self, kind: TokenTreeKind, expected: &str) -> Option<TokenTree> {
+        if self.tokenstream.peek().is_some_and(|tok| tok.kind == kind) {
+            self.tokenstream.next()
+        } else {
+            self.error_expected(expected);
+            None
+        }
+    }
+
+    /// Consumes the next token if it is a group delimited by `mode`.
+    pub(super) fn expect_group(&mut self, mode: GroupMode, expected: &str) -> Option<Group> {
+        if !peek_is!(self.tokenstream, TokenTreeKind::Group { mode: ref m, .. } if *m == mode) {
+            self.error_expected(expected);
+            return None;
+        }
+        let token = self.tokenstream.next()?;
+        let TokenTreeKind::Group { children, open, .. } = token.kind else {
+            unreachable!("group was just peeked");
+        };
+        Some(Group {
+            children,
+            open,
+            span: token.span,
+        })
+    }
+
+    /// The empty span just past the last consumed token, where something
+    /// that is missing should have been.
+    pub(super) fn end_span(&self) -> Span {
+        let prev = self.tokenstream.prev_span;
+        Span {
+            start: prev.end,
+            ..prev
+        }
+    }
+
+    /// The span from `start` through the last consumed token.
+    pub(super) fn span_from(&self, start: Span) -> Span {
+        start.to(self.tokenstream.prev_span)
+    }
+
+    /// Records an error, unless the statement being parsed already has one.
+    pub(super) fn report(&mut self, error: Diagnostic) {
+        if !self.recovering {
+            self.errors.push(error);
+        }
+        self.recovering = true;
+    }
+
+    /// Skips ahead to where the next statement most likely starts: just past
+    /// a `;`, or at a keyword that begins a declaration. At least one token
+    /// is skipped if the failed construct starting at `start` consumed none,
+    /// so that parsing always makes progress.
+    pub(super) fn synchronize(&mut self, start: Span) {
+        if self.tokenstream.peek().is_some_and(|tok| tok.span == start)
+            && self
+                .tokenstream
+                .next()
+                .is_some_and(|tok| tok.kind == TokenTreeKind::SemiColon)
+        {
+            return;
+        }
+        while let Some(token) = self.tokenstream.peek() {
+            match token.kind {
+                TokenTreeKind::SemiColon => {
+                    self.tokenstream.next();
+                    return;
+                }
+                TokenTreeKind::Fn
+                | TokenTreeKind::Struct
+                | TokenTreeKind::Enum
+                | TokenTreeKind::Be => return,
+                _ => {
+                    self.tokenstream.next();
+                }
+            }
+        }
+    }
+
+    /// Whether the next token is one the lexer could not make sense of. It
+    /// has already been reported, so rather than reporting it again the
+    /// statement is just marked as having gone wrong.
+    pub(super) fn at_lexical_error(&mut self) -> bool {
+        let at_error = peek_is!(self.tokenstream, TokenTreeKind::Error { .. });
+        if at_error {
+            self.recovering = true;
+        }
+        at_error
+    }
+
+    pub(super) fn error_expected(&mut self, expected: &str) {
+        if self.at_lexical_error() {
+            return;
+        }
+        let (message, span) = match self.tokenstream.peek() {
+            Some(token) => (
+                format!("Expected {}, found {}", expected, token.kind.describe()),
+                token.span,
+            ),
+            None => (
+                format!("Expected {}, found end of input", expected),
+                self.end_span(),
+            ),
+        };
+        self.report(Diagnostic::error(codes::UNEXPECTED_TOKEN, message, span));
+    }
+
+    /// Records a missing `;` after the expression at `span`.
+    pub(super) fn error_missing_semicolon(&mut self, span: Span) {
+        self.report(
+            Diagnostic::error(
+                codes::MISSING_SEMICOLON,
+                "Expected ';' after expression",
+                span,
+            )
+            .with_suggestion(
+                "add a semicolon",
+                Span {
+                    start: span.end,
+                    ..span
+                },
+                ";",
+            ),
+        );
+    }
+
+    /// Runs `parse` with the cursor temporarily moved onto the children of a
+    /// group opened at `open`.
+    pub(super) fn within_group<T>(
+        &mut self,
+        children: Vec<TokenTree>,
+        open: Span,
+        parse: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let outer = std::mem::replace(&mut self.tokenstream, TokenCursor::new(children, open));
+        let result = parse(self);
+        self.tokenstream = outer;
+        result
+    }
 }
 
-enum Precedence {
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+pub(super) enum Precedence {
     Lowest = 1,
+    Assign,      // = or +=
+    Pipe,        // |>
+    Or,          // ||
+    And,         // &&
+    BitOr,       // |
+    BitXor,      // ^
+    BitAnd,      // &
     Equals,      // ==
     LessGreater, // > or <
+    Shift,       // << or >>
     Sum,         // +
     Product,     // *
     Prefix,      // -X or !X
     Call,        // myFunction(X)
-    Index,       // array[index]
+    Index,       // array[index] or value.field
+}
+
+impl Precedence {
+    /// Binding power of `op` when it appears between two operands,
+    /// or `None` if `op` is not a binary operator.
+    pub(super) fn infix(op: &Op) -> Option<Self> {
+        let precedence = match op {
+            op if op.is_assignment() => Precedence::Assign,
+            Op::Pipe => Precedence::Pipe,
+            Op::Or => Precedence::Or,
+            Op::And => Precedence::And,
+            Op::BitOr => Precedence::BitOr,
+            Op::BitXor => Precedence::BitXor,
+            Op::BitAnd => Precedence::BitAnd,
+            Op::EqualTo | Op::NotEqual => Precedence::Equals,
+            Op::GreaterThan | Op::LessThan | Op::GreaterThanOrEqual | Op::LessThanOrEqual => {
+                Precedence::LessGreater
+            }
+            Op::ShiftLeft | Op::ShiftRight => Precedence::Shift,
+            Op::Plus | Op::Minus => Precedence::Sum,
+            Op::Multiply | Op::Divide | Op::Modulo => Precedence::Product,
+            _ => return None,
+        };
+        Some(precedence)
+    }
 }
@@ -0,0 +1,173 @@
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    parser::{
+        Expression, Spanned, Statement,
+        types::ast::{BlockExpression, CallExpression, Ident, InterpolatedPart, PipeExpression},
+    },
+};
+
+/// Rewrites surface syntax into the smaller core language:
+///
+/// * `a |> f(x, _)` becomes `f(x, a)`,
+/// * `a |> f(x)` becomes `f(a, x)` and `a |> f` becomes `f(a)`.
+pub struct Desugarer {
+    errors: Vec<Diagnostic>,
+}
+
+impl Desugarer {
+    pub fn desugar(statements: &mut [Statement]) -> Vec<Diagnostic> {
+        let mut desugarer = Desugarer { errors: Vec::new() };
+        desugarer.desugar_statements(statements);
+        desugarer.errors
+    }
+
+    fn desugar_statements(&mut self, statements: &mut [Statement]) {
+        for stmt in statements {
+            self.desugar_statement(stmt);
+        }
+    }
+
+    fn desugar_statement(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Be(be_stmt) => self.desugar_expression(&mut be_stmt.value),
+            Statement::Return(ret_stmt) => self.desugar_expression(&mut ret_stmt.expr),
+            Statement::Expression(expr_stmt) => self.desugar_expression(&mut expr_stmt.expr),
+            Statement::Fn(fn_stmt) => self.desugar_block(&mut fn_stmt.body),
+            Statement::Struct(_) | Statement::Enum(_) | Statement::Error(_) => {}
+        }
+    }
+
+    fn desugar_block(&mut self, block: &mut BlockExpression) {
+        self.desugar_statements(&mut block.statements);
+        if let Some(tail) = &mut block.tail {
+            self.desugar_expression(tail);
+        }
+    }
+
+    fn desugar_expression(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Ident(ident) => {
+                if ident.name == "_" {
+                    self.errors.push(Diagnostic::error(
+                        codes::INVALID_PIPE,
+                        "Placeholder '_' is only allowed as an argument of a piped call",
+                        ident.span,
+                    ));
+                }
+            }
+            Expression::Path(_) | Expression::Literal(_) | Expression::Error(_) => {}
+            Expression::Prefix(prefix) => self.desugar_expression(&mut prefix.right),
+            Expression::Infix(infix) => {
+                self.desugar_expression(&mut infix.left);
+                self.desugar_expression(&mut infix.right);
+            }
+            Expression::Block(block) => self.desugar_block(block),
+            Expression::If(if_expr) => {
+                self.desugar_expression(&mut if_expr.condition);
+                self.desugar_block(&mut if_expr.consequence);
+                if let Some(alternative) = &mut if_expr.alternative {
+                    self.desugar_expression(alternative);
+                }
+            }
+            Expression::While(while_expr) => {
+                self.desugar_expression(&mut while_expr.condition);
+                self.desugar_block(&mut while_expr.body);
+            }
+            Expression::Assign(assign) => {
+                self.desugar_expression(&mut assign.target);
+                self.desugar_expression(&mut assign.value);
+            }
+            Expression::Pipe(_) => {
+                let hole = Expression::Ident(Ident {
+                    name: "_".to_string(),
+                    span: expr.span(),
+                });
+                let Expression::Pipe(pipe) = std::mem::replace(expr, hole) else {
+                    unreachable!();
+                };
+                *expr = self.desugar_pipe(pipe);
+            }
+            Expression::Call(call) => {
+                self.desugar_expression(&mut call.callee);
+                call.args
+                    .iter_mut()
+                    .for_each(|arg| self.desugar_expression(arg));
+            }
+            Expression::Index(index) => {
+                self.desugar_expression(&mut index.target);
+                self.desugar_expression(&mut index.index);
+            }
+            Expression::Field(field) => self.desugar_expression(&mut field.target),
+            Expression::Lambda(lambda) => self.desugar_expression(&mut lambda.body),
+            Expression::Interpolated(interpolated) => {
+                for part in &mut interpolated.parts {
+                    if let InterpolatedPart::Expr(expr) = part {
+                        self.desugar_expression(expr);
+                    }
+                }
+            }
+        }
+    }
+
+    fn desugar_pipe(&mut self, pipe: PipeExpression) -> Expression {
+        let PipeExpression {
+            mut left,
+            mut right,
+            span,
+        } = pipe;
+        self.desugar_expression(&mut left);
+        let mut call = match *right {
+            Expression::Call(call) => call,
+            callee @ (Expression::Ident(_) | Expression::Path(_)) => CallExpression {
+                span: callee.span(),
+                callee: Box::new(callee),
+                args: Vec::new(),
+            },
+            _ => {
+                self.errors.push(
+                    Diagnostic::error(
+                        codes::INVALID_PIPE,
+                        "Expected a function or call after '|>'",
+                        right.span(),
+                    )
+                    .with_help("pipe into a call such as 'f(x)' or 'f(x, _)'"),
+                );
+                self.desugar_expression(&mut right);
+                return Expression::Pipe(PipeExpression { left, right, span });
+            }
+        };
+        self.desugar_expression(&mut call.callee);
+        call.args
+            .iter_mut()
+            .filter(|arg| !arg.is_placeholder())
+            .for_each(|arg| self.desugar_expression(arg));
+
+        let mut placeholders = call
+            .args
+            .iter()
+            .enumerate()
+            .filter(|(_, arg)| arg.is_placeholder())
+            .map(|(index, _)| index);
+        match (placeholders.next(), placeholders.next()) {
+            (None, _) => call.args.insert(0, *left),
+            (Some(index), None) => call.args[index] = *left,
+            (Some(_), Some(second)) => {
+                let first = call.args.iter().find(|arg| arg.is_placeholder()).unwrap();
+                self.errors.push(
+                    Diagnostic::error(
+                        codes::INVALID_PIPE,
+                        "Piped call has more than one '_' placeholder",
+                        call.args[second].span(),
+                    )
+                    .with_label(first.span(), "first placeholder here"),
+                );
+                return Expression::Pipe(PipeExpression {
+                    left,
+                    right: Box::new(Expression::Call(call)),
+                    span,
+                });
+            }
+        }
+        Expression::Call(call)
+    }
+}
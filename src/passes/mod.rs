@@ -0,0 +1,2 @@
+pub mod desugar;
+pub mod mutability;
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    parser::{
+        Expression, Spanned, Statement,
+        types::ast::{BlockExpression, Ident, InterpolatedPart},
+    },
+    span::Span,
+};
+
+struct Binding {
+    is_mut: bool,
+    span: Span,
+}
+
+/// Rejects assignments to bindings that were not declared with `be mut`.
+///
+/// Names that are not bound by a `be` or a parameter in scope (globals,
+/// functions, typos) are left alone until there is proper name resolution.
+pub struct MutabilityChecker {
+    scopes: Vec<HashMap<String, Binding>>,
+    errors: Vec<Diagnostic>,
+}
+
+impl MutabilityChecker {
+    pub fn check(statements: &[Statement]) -> Vec<Diagnostic> {
+        let mut checker = MutabilityChecker {
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+        };
+        checker.check_statements(statements);
+        checker.errors
+    }
+
+    fn declare(&mut self, ident: &Ident, is_mut: bool) {
+        let scope = self.scopes.last_mut().expect("there is always a scope");
+        scope.insert(
+            ident.name.clone(),
+            Binding {
+                is_mut,
+                span: ident.span,
+            },
+        );
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for stmt in statements {
+            self.check_statement(stmt);
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Be(be_stmt) => {
+                self.check_expression(&be_stmt.value);
+                self.declare(&be_stmt.ident, be_stmt.is_mut);
+            }
+            Statement::Return(ret_stmt) => self.check_expression(&ret_stmt.expr),
+            Statement::Expression(expr_stmt) => self.check_expression(&expr_stmt.expr),
+            Statement::Fn(fn_stmt) => {
+                self.scopes.push(HashMap::new());
+                for param in &fn_stmt.params {
+                    self.declare(&param.ident, false);
+                }
+                self.check_block(&fn_stmt.body);
+                self.scopes.pop();
+            }
+            Statement::Struct(_) | Statement::Enum(_) | Statement::Error(_) => {}
+        }
+    }
+
+    fn check_block(&mut self, block: &BlockExpression) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(&block.statements);
+        if let Some(tail) = &block.tail {
+            self.check_expression(tail);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Ident(_)
+            | Expression::Path(_)
+            | Expression::Literal(_)
+            | Expression::Error(_) => {}
+            Expression::Prefix(prefix) => self.check_expression(&prefix.right),
+            Expression::Infix(infix) => {
+                self.check_expression(&infix.left);
+                self.check_expression(&infix.right);
+            }
+            Expression::Block(block) => self.check_block(block),
+            Expression::If(if_expr) => {
+                self.check_expression(&if_expr.condition);
+                self.check_block(&if_expr.consequence);
+                if let Some(alternative) = &if_expr.alternative {
+                    self.check_expression(alternative);
+                }
+            }
+            Expression::While(while_expr) => {
+                self.check_expression(&while_expr.condition);
+                self.check_block(&while_expr.body);
+            }
+            Expression::Assign(assign) => {
+                self.check_expression(&assign.value);
+                self.check_assign_target(&assign.target);
+            }
+            Expression::Pipe(pipe) => {
+                self.check_expression(&pipe.left);
+                self.check_expression(&pipe.right);
+            }
+            Expression::Call(call) => {
+                self.check_expression(&call.callee);
+                call.args.iter().for_each(|arg| self.check_expression(arg));
+            }
+            Expression::Index(index) => {
+                self.check_expression(&index.target);
+                self.check_expression(&index.index);
+            }
+            Expression::Field(field) => self.check_expression(&field.target),
+            Expression::Lambda(lambda) => {
+                self.scopes.push(HashMap::new());
+                for param in &lambda.params {
+                    self.declare(&param.ident, false);
+                }
+                self.check_expression(&lambda.body);
+                self.scopes.pop();
+            }
+            Expression::Interpolated(interpolated) => {
+                for part in &interpolated.parts {
+                    if let InterpolatedPart::Expr(expr) = part {
+                        self.check_expression(expr);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_assign_target(&mut self, target: &Expression) {
+        // Assigning through `a[i]` or `a.b` mutates the binding `a` as well.
+        let ident = match target {
+            Expression::Ident(ident) => ident,
+            Expression::Index(index) => return self.check_assign_target(&index.target),
+            Expression::Field(field) => return self.check_assign_target(&field.target),
+            _ => return,
+        };
+        if let Some(binding) = self.lookup(&ident.name)
+            && !binding.is_mut
+        {
+            let error = Diagnostic::error(
+                codes::ASSIGN_TO_IMMUTABLE,
+                format!("Cannot assign to immutable binding '{}'", ident.name),
+                target.span(),
+            )
+            .with_label(binding.span, "declared without 'mut' here");
+            self.errors.push(error);
+        }
+    }
+}
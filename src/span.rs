@@ -0,0 +1,103 @@
+/// How many columns a tab advances by, wherever the width of source text matters.
+pub const TAB_WIDTH: usize = 4;
+
+/// The number of columns `text` takes up on screen, with tabs expanded.
+pub fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Identifies a file added to a [`SourceMap`].
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct FileId(usize);
+
+/// A range of bytes `start..end` in one source file.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Span { file, start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        debug_assert_eq!(self.file, other.file, "spans from different files");
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// Owns the source text of every file being compiled and maps byte offsets
+/// back to the lines and columns users see.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+pub struct SourceFile {
+    /// The path the file was read from, as given by the user.
+    pub name: String,
+    pub src: String,
+    /// Byte offset at which each line starts; the first line starts at 0.
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, name: String, src: String) -> FileId {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.files.push(SourceFile {
+            name,
+            src,
+            line_starts,
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    /// The 1-based line and column (counted in characters) where `span` starts.
+    pub fn line_col(&self, span: Span) -> (usize, usize) {
+        self.file(span.file).line_col(span.start)
+    }
+}
+
+impl SourceFile {
+    /// The 1-based line and column (counted in characters) of byte `offset`.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.src[self.line_starts[line]..offset].chars().count() + 1;
+        (line + 1, column)
+    }
+
+    /// The text of the 1-based `line`, without its line ending.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.src.len(), |&next| next - 1);
+        self.src[start..end].trim_end_matches('\r')
+    }
+
+    /// The byte offset at which the 1-based `line` starts.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line - 1]
+    }
+}
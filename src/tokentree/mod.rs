@@ -1,16 +1,23 @@
 use derivative::Derivative;
 
-use crate::lexer::types::{Op, Token, TokenKind};
+use crate::{
+    diagnostic::{Diagnostic, codes},
+    lexer::types::{FloatSuffix, IntSuffix, Op, StringPart, Token, TokenKind},
+    span::{SourceFile, Span, width},
+};
+use std::vec::IntoIter;
 
 #[derive(PartialEq, Derivative)]
 #[derivative(Debug)]
 pub struct TokenTree {
     pub kind: TokenTreeKind,
+    /// For groups, the span from the opening through the closing delimiter.
     #[derivative(Debug = "ignore")]
-    pub pos: (usize, usize),
+    pub span: Span,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Derivative)]
+#[derivative(Debug)]
 pub enum TokenTreeKind {
     Be,
     Mut,
@@ -28,44 +35,166 @@ pub enum TokenTreeKind {
 
     Comma,
     Colon,
+    PathSep,
+    Dot,
+    FatArrow,
     SemiColon,
 
     Group {
         mode: GroupMode,
         children: Vec<TokenTree>,
+        #[derivative(Debug = "ignore")]
+        open: Span,
+        #[derivative(Debug = "ignore")]
+        close: Span,
     },
 
+    DocComment(String),
+
     Identifier(String),
+    Char(char),
     String(String),
-    Integer(i64),
-    Float(f64),
+    RawString(String),
+    ByteString(Vec<u8>),
+    MultilineString(String),
+    InterpolatedString(Vec<StringPart<TokenTree>>),
+    Integer {
+        value: u64,
+        suffix: Option<IntSuffix>,
+    },
+    Float {
+        value: f64,
+        suffix: Option<FloatSuffix>,
+    },
 
     Op(Op),
+    Error {
+        text: String,
+        diagnostic: Box<Diagnostic>,
+    },
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum GroupMode {
     Parens,
     Curly,
     Bracket,
 }
 
+impl GroupMode {
+    pub fn open(&self) -> char {
+        match self {
+            GroupMode::Parens => '(',
+            GroupMode::Curly => '{',
+            GroupMode::Bracket => '[',
+        }
+    }
+
+    pub fn close(&self) -> char {
+        match self {
+            GroupMode::Parens => ')',
+            GroupMode::Curly => '}',
+            GroupMode::Bracket => ']',
+        }
+    }
+}
+
+impl TokenTreeKind {
+    /// The token as error messages refer to it: its spelling in quotes, or
+    /// what kind of token it is where the spelling alone would be unclear.
+    pub fn describe(&self) -> String {
+        let spelling = match self {
+            TokenTreeKind::Be => "be",
+            TokenTreeKind::Mut => "mut",
+            TokenTreeKind::If => "if",
+            TokenTreeKind::Else => "else",
+            TokenTreeKind::While => "while",
+            TokenTreeKind::Fn => "fn",
+            TokenTreeKind::Return => "return",
+            TokenTreeKind::Struct => "struct",
+            TokenTreeKind::Enum => "enum",
+            TokenTreeKind::Boolean(true) => "true",
+            TokenTreeKind::Boolean(false) => "false",
+            TokenTreeKind::Comma => ",",
+            TokenTreeKind::Colon => ":",
+            TokenTreeKind::PathSep => "::",
+            TokenTreeKind::Dot => ".",
+            TokenTreeKind::FatArrow => "=>",
+            TokenTreeKind::SemiColon => ";",
+            TokenTreeKind::Group { mode, .. } => {
+                return format!("'{}...{}'", mode.open(), mode.close());
+            }
+            TokenTreeKind::Op(op) => op.symbol(),
+            TokenTreeKind::Identifier(name) => return format!("identifier `{}`", name),
+            TokenTreeKind::DocComment(_) => return "doc comment".to_string(),
+            TokenTreeKind::Char(_) => return "character literal".to_string(),
+            TokenTreeKind::String(_)
+            | TokenTreeKind::RawString(_)
+            | TokenTreeKind::MultilineString(_)
+            | TokenTreeKind::InterpolatedString(_) => return "string literal".to_string(),
+            TokenTreeKind::ByteString(_) => return "byte string literal".to_string(),
+            TokenTreeKind::Integer { .. } => return "integer literal".to_string(),
+            TokenTreeKind::Float { .. } => return "float literal".to_string(),
+            TokenTreeKind::Error { text, .. } => text,
+        };
+        format!("'{}'", spelling)
+    }
+}
+
 impl TokenTree {
+    /// Groups `tokens` into trees. Unbalanced delimiters are reported and
+    /// repaired rather than stopping the build, so the trees are always
+    /// usable by the parser.
     pub fn parse_from_tokens(
-        iter: &mut impl Iterator<Item = Token>,
-    ) -> Result<Vec<TokenTree>, String> {
+        tokens: Vec<Token>,
+        source: &SourceFile,
+    ) -> (Vec<TokenTree>, Vec<Diagnostic>) {
+        let mut builder = Builder::new(tokens, source);
+        let tokenstream = builder.parse_stream();
+        (tokenstream, builder.errors)
+    }
+}
+
+struct Builder<'a> {
+    tokens: IntoIter<Token>,
+    source: &'a SourceFile,
+    /// The modes of the groups currently being built, innermost last.
+    open: Vec<GroupMode>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(tokens: Vec<Token>, source: &'a SourceFile) -> Self {
+        Builder {
+            tokens: tokens.into_iter(),
+            source,
+            open: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn parse_stream(&mut self) -> Vec<TokenTree> {
         let mut tokenstream = Vec::new();
-        while let Some(token) = iter.next() {
-            let tokentree = Self::token_to_tokentree(token, iter)?;
-            tokenstream.push(tokentree);
+        while let Some(token) = self.tokens.next() {
+            self.push_tree(token, &mut tokenstream);
         }
-        Ok(tokenstream)
+        tokenstream
+    }
+
+    /// The tokens of an interpolation hole are grouped on their own, since
+    /// delimiters can't be balanced across the string around them.
+    fn parse_hole(&mut self, tokens: Vec<Token>) -> Vec<TokenTree> {
+        let mut builder = Builder::new(tokens, self.source);
+        let tokenstream = builder.parse_stream();
+        self.errors.append(&mut builder.errors);
+        tokenstream
     }
 
-    fn token_to_tokentree(
-        token: Token,
-        iter: &mut impl Iterator<Item = Token>,
-    ) -> Result<TokenTree, String> {
+    /// Turns `token`, and for an opening delimiter everything up to its
+    /// closer, into trees appended to `out`. That is usually a single tree,
+    /// but a group closed early by recovery hands back the trees after the
+    /// guessed closer, which belong to the enclosing group.
+    fn push_tree(&mut self, token: Token, out: &mut Vec<TokenTree>) {
         let kind = match token.kind {
             TokenKind::Be => TokenTreeKind::Be,
             TokenKind::Mut => TokenTreeKind::Mut,
@@ -82,72 +211,273 @@ impl TokenTree {
 
             TokenKind::Comma => TokenTreeKind::Comma,
             TokenKind::Colon => TokenTreeKind::Colon,
+            TokenKind::PathSep => TokenTreeKind::PathSep,
+            TokenKind::Dot => TokenTreeKind::Dot,
+            TokenKind::FatArrow => TokenTreeKind::FatArrow,
             TokenKind::SemiColon => TokenTreeKind::SemiColon,
 
             TokenKind::LParen | TokenKind::LCurly | TokenKind::LBracket => {
-                let mode = TokenTree::token_to_groupmode(&token).unwrap();
-                return Self::parse_group(mode, token, iter);
+                let mode = token_to_groupmode(&token).unwrap();
+                self.parse_group(mode, token.span, out);
+                return;
             }
+            // Groups consume their own closers, so this one has no opener.
             TokenKind::RParen | TokenKind::RCurly | TokenKind::RBracket => {
-                return Err(format!(
-                    "Unmatched closing bracket at line {}, column {}",
-                    token.pos.0, token.pos.1
+                let mode = token_to_groupmode(&token).unwrap();
+                self.errors.push(Diagnostic::error(
+                    codes::UNMATCHED_DELIMITER,
+                    format!("Unmatched closing delimiter '{}'", mode.close()),
+                    token.span,
                 ));
+                return;
             }
 
+            TokenKind::DocComment(s) => TokenTreeKind::DocComment(s),
+
             TokenKind::Identifier(s) => TokenTreeKind::Identifier(s),
+            TokenKind::Char(c) => TokenTreeKind::Char(c),
             TokenKind::String(s) => TokenTreeKind::String(s),
-            TokenKind::Integer(i) => TokenTreeKind::Integer(i),
-            TokenKind::Float(f) => TokenTreeKind::Float(f),
-
+            TokenKind::RawString(s) => TokenTreeKind::RawString(s),
+            TokenKind::ByteString(b) => TokenTreeKind::ByteString(b),
+            TokenKind::MultilineString(s) => TokenTreeKind::MultilineString(s),
+            TokenKind::InterpolatedString(parts) => TokenTreeKind::InterpolatedString(
+                parts
+                    .into_iter()
+                    .map(|part| match part {
+                        StringPart::Literal(s) => StringPart::Literal(s),
+                        StringPart::Hole(tokens) => StringPart::Hole(self.parse_hole(tokens)),
+                    })
+                    .collect(),
+            ),
+            TokenKind::Integer { value, suffix } => TokenTreeKind::Integer { value, suffix },
+            TokenKind::Float { value, suffix } => TokenTreeKind::Float { value, suffix },
             TokenKind::Op(op) => TokenTreeKind::Op(op),
+            TokenKind::Error { text, diagnostic } => TokenTreeKind::Error { text, diagnostic },
         };
-        Ok(TokenTree {
+        out.push(TokenTree {
             kind,
-            pos: token.pos,
-        })
-    }
-
-    fn token_to_groupmode(token: &Token) -> Option<GroupMode> {
-        match token.kind {
-            TokenKind::RParen | TokenKind::LParen => Some(GroupMode::Parens),
-            TokenKind::RCurly | TokenKind::LCurly => Some(GroupMode::Curly),
-            TokenKind::RBracket | TokenKind::LBracket => Some(GroupMode::Bracket),
-            _ => None,
-        }
+            span: token.span,
+        });
     }
 
-    fn parse_group(
-        mode: GroupMode,
-        opening_token: Token,
-        iter: &mut impl Iterator<Item = Token>,
-    ) -> Result<TokenTree, String> {
+    fn parse_group(&mut self, mode: GroupMode, open: Span, out: &mut Vec<TokenTree>) {
+        self.open.push(mode);
         let mut children = Vec::new();
-        let closing_token = TokenTree::token_to_groupmode(&opening_token);
-        while let Some(token) = iter.next() {
-            match token.kind {
-                TokenKind::RParen | TokenKind::RCurly | TokenKind::RBracket => {
-                    if TokenTree::token_to_groupmode(&token) == closing_token {
-                        return Ok(TokenTree {
-                            kind: TokenTreeKind::Group { mode, children },
-                            pos: opening_token.pos,
-                        });
-                    } else {
-                        return Err(format!(
-                            "Mismatched closing bracket at line {}, column {}: expected {:?}, found {:?}",
-                            token.pos.0, token.pos.1, closing_token, token.kind
-                        ));
-                    }
-                }
-                _ => {
-                    let tokentree = Self::token_to_tokentree(token, iter)?;
-                    children.push(tokentree);
+        let close = loop {
+            let Some(token) = self.tokens.as_slice().first() else {
+                break None;
+            };
+            if !is_closer(token) {
+                let token = self.tokens.next().unwrap();
+                self.push_tree(token, &mut children);
+                continue;
+            }
+            let found = token_to_groupmode(token).unwrap();
+            let start = token.span.start;
+            let enclosing = &self.open[..self.open.len() - 1];
+            if found == mode {
+                // A closer starting a line indented less than ours was
+                // probably written for an enclosing group, so ours was never
+                // closed. Balanced input is never regrouped, however it is
+                // indented.
+                let outdented = self
+                    .line_indentation(start)
+                    .is_some_and(|indent| indent < self.indentation(open.start));
+                if outdented && enclosing.contains(&found) && !self.balanced_after_closer() {
+                    break None;
                 }
+                break Some(self.tokens.next().unwrap().span);
+            }
+            // A wrong closer on the line the group opened on, or starting a
+            // line indented like that one, is most likely a typo for ours.
+            let same_line = self.source.line_col(start).0 == self.source.line_col(open.start).0;
+            let aligned = self.line_indentation(start) == Some(self.indentation(open.start));
+            if same_line || aligned {
+                let token = self.tokens.next().unwrap();
+                self.errors.push(
+                    Diagnostic::error(
+                        codes::UNMATCHED_DELIMITER,
+                        format!(
+                            "Mismatched closing delimiter: expected '{}', found '{}'",
+                            mode.close(),
+                            found.close()
+                        ),
+                        token.span,
+                    )
+                    .with_label(open, "group opened here")
+                    .with_suggestion(
+                        format!("close the group with '{}'", mode.close()),
+                        token.span,
+                        mode.close().to_string(),
+                    ),
+                );
+                break Some(token.span);
+            }
+            // Otherwise, if it closes an enclosing group then this one was
+            // never closed; leave the closer for that group.
+            if enclosing.contains(&found) {
+                break None;
+            }
+            // A closer nothing is waiting for is reported as unmatched and
+            // skipped.
+            let token = self.tokens.next().unwrap();
+            self.push_tree(token, &mut children);
+        };
+        self.open.pop();
+
+        let (close, rest) = match close {
+            Some(close) => (close, Vec::new()),
+            None => self.recover_unclosed(mode, open, &mut children),
+        };
+        out.push(TokenTree {
+            kind: TokenTreeKind::Group {
+                mode,
+                children,
+                open,
+                close,
+            },
+            span: open.to(close),
+        });
+        out.extend(rest);
+    }
+
+    /// Whether the tokens after the next one, which closes the innermost
+    /// open group, close every enclosing group in order and nothing else.
+    fn balanced_after_closer(&self) -> bool {
+        let mut open = self.open[..self.open.len() - 1].to_vec();
+        for token in &self.tokens.as_slice()[1..] {
+            let Some(mode) = token_to_groupmode(token) else {
+                continue;
+            };
+            if !is_closer(token) {
+                open.push(mode);
+            } else if open.pop() != Some(mode) {
+                return false;
             }
         }
-        Err(format!(
-            "Unclosed bracket starting at line {}, column {}",
-            opening_token.pos.0, opening_token.pos.1
-        ))
+        open.is_empty()
+    }
+
+    /// Guesses where the closer of an unclosed group belongs and splits
+    /// `children` there, returning an empty span at the guessed spot and the
+    /// trees that follow it. The group is taken to end before the first tree
+    /// that starts a line indented no deeper than the line the group opened
+    /// on, or, since only blocks hold statements, before the first `;` of a
+    /// parenthesised or bracketed group.
+    fn recover_unclosed(
+        &mut self,
+        mode: GroupMode,
+        open: Span,
+        children: &mut Vec<TokenTree>,
+    ) -> (Span, Vec<TokenTree>) {
+        let indent = self.indentation(open.start);
+        let end = children
+            .iter()
+            .position(|child| {
+                (mode != GroupMode::Curly && child.kind == TokenTreeKind::SemiColon)
+                    || self
+                        .line_indentation(child.span.start)
+                        .is_some_and(|child_indent| child_indent <= indent)
+            })
+            .unwrap_or(children.len());
+        let rest = children.split_off(end);
+        let at = children.last().map_or(open.end, |child| child.span.end);
+        let close = Span::new(open.file, at, at);
+        self.errors.push(
+            Diagnostic::error(
+                codes::UNMATCHED_DELIMITER,
+                format!("Unclosed delimiter '{}'", mode.open()),
+                open,
+            )
+            .with_label(open, "group opened here")
+            .with_label(
+                close,
+                format!("the missing '{}' probably belongs here", mode.close()),
+            )
+            .with_suggestion(
+                format!("add a closing '{}'", mode.close()),
+                close,
+                mode.close().to_string(),
+            ),
+        );
+        (close, rest)
+    }
+
+    /// The width of the whitespace that starts the line containing `offset`.
+    fn indentation(&self, offset: usize) -> usize {
+        let (line, _) = self.source.line_col(offset);
+        let text = self.source.line(line);
+        width(&text[..text.len() - text.trim_start().len()])
+    }
+
+    /// Like [`Builder::indentation`], but only if nothing but that
+    /// whitespace comes before `offset` on its line.
+    fn line_indentation(&self, offset: usize) -> Option<usize> {
+        let (line, _) = self.source.line_col(offset);
+        let before = &self.source.src[self.source.line_start(line)..offset];
+        before.trim_start().is_empty().then(|| width(before))
+    }
+}
+
+fn token_to_groupmode(token: &Token) -> Option<GroupMode> {
+    match token.kind {
+        TokenKind::RParen | TokenKind::LParen => Some(GroupMode::Parens),
+        TokenKind::RCurly | TokenKind::LCurly => Some(GroupMode::Curly),
+        TokenKind::RBracket | TokenKind::LBracket => Some(GroupMode::Bracket),
+        _ => None,
+    }
+}
+
+fn is_closer(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::RParen | TokenKind::RCurly | TokenKind::RBracket
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::LexerCursor, span::SourceMap};
+
+    fn build(src: &str) -> (Vec<TokenTree>, Vec<Diagnostic>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test.ktn".to_string(), src.to_string());
+        let tokens = LexerCursor::new(&source_map.file(file).src, file).collect();
+        TokenTree::parse_from_tokens(tokens, source_map.file(file))
+    }
+
+    #[test]
+    fn misindented_balanced_input_is_grouped_as_written() {
+        let (trees, errors) = build("fn main() {\n    if x {\n  }\n}\n");
+        assert!(errors.is_empty());
+        let [.., body] = trees.as_slice() else {
+            panic!("expected a function body");
+        };
+        let TokenTreeKind::Group { children, .. } = &body.kind else {
+            panic!("expected the body to be a group");
+        };
+        assert!(matches!(
+            children.as_slice(),
+            [
+                TokenTree {
+                    kind: TokenTreeKind::If,
+                    ..
+                },
+                _,
+                TokenTree {
+                    kind: TokenTreeKind::Group { .. },
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn outdented_closer_ends_the_enclosing_group() {
+        let (trees, errors) = build("fn main() {\n    if x {\n        foo();\n}\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, codes::UNMATCHED_DELIMITER);
+        assert_eq!(trees.len(), 4);
     }
 }